@@ -0,0 +1,70 @@
+/*
+ * =============================================================================
+ * WinHider Benchmarks - Preview Downscaling
+ * =============================================================================
+ *
+ * Filename: downscale.rs
+ * Description: Times every filter on synthetic frames at common monitor sizes
+ *              against each preview quality target.
+ *
+ * Usage: cargo bench -p MozillaFirefox --bench downscale
+ * =============================================================================
+ */
+
+// Benches build with cfg(test) but no harness, so the module's tests are unused here
+#[path = "../src/downscale.rs"]
+#[allow(dead_code)]
+mod downscale;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use downscale::{downscale, fit_size, quality_params, Filter};
+
+const SOURCES: [(u32, u32); 3] = [(1920, 1080), (2560, 1440), (3840, 2160)];
+const FILTERS: [Filter; 3] = [Filter::Nearest, Filter::Bilinear, Filter::Box];
+const MIN_TIME: Duration = Duration::from_millis(500);
+
+/// Diagonal colour bands with some noise, so filters cannot shortcut.
+fn synthetic_frame(width: u32, height: u32) -> Vec<u8> {
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+    let mut seed = 0x2545_f491u32;
+    for y in 0..height {
+        for x in 0..width {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            let band = ((x + y) / 8) as u8;
+            data.extend_from_slice(&[band, band.wrapping_mul(3), (seed & 0x1f) as u8, 255]);
+        }
+    }
+    data
+}
+
+fn main() {
+    println!("{:<11} {:<11} {:<9} {:>10} {:>9}", "SOURCE", "TARGET", "FILTER", "MS/FRAME", "FPS");
+    for (src_w, src_h) in SOURCES {
+        let frame = synthetic_frame(src_w, src_h);
+        for quality in 1..=3 {
+            let (max_w, max_h, _) = quality_params(quality);
+            let (dst_w, dst_h) = fit_size(src_w, src_h, max_w, max_h);
+            for filter in FILTERS {
+                let start = Instant::now();
+                let mut runs = 0u32;
+                while start.elapsed() < MIN_TIME {
+                    black_box(downscale(black_box(&frame), src_w, src_h, dst_w, dst_h, filter).unwrap());
+                    runs += 1;
+                }
+                let per_frame = start.elapsed().as_secs_f64() * 1000.0 / runs as f64;
+                println!(
+                    "{:<11} {:<11} {:<9} {:>10.2} {:>9.1}",
+                    format!("{}x{}", src_w, src_h),
+                    format!("{}x{}", dst_w, dst_h),
+                    format!("{:?}", filter),
+                    per_frame,
+                    1000.0 / per_frame
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,417 @@
+/*
+ * =============================================================================
+ * WinHider Download - Verified Installer Downloads
+ * =============================================================================
+ *
+ * Filename: download.rs
+ * Description: Downloads the installer of a release, resuming an interrupted
+ *              download, and checks it against the SHA-256 published with the
+ *              release before it may be run.
+ *
+ * Notes:
+ * - The checksum comes from a `<installer>.sha256` asset, or failing that a
+ *   `SHA256SUMS` asset, in the usual `sha256sum` format. A release without
+ *   one is refused rather than run unverified.
+ * - Bytes land in `<installer>.part` and are appended to on the next attempt
+ *   using an HTTP range request. A server that ignores the range sends the
+ *   whole file again, which replaces the partial file.
+ * - A file that fails the check is deleted, so a retry starts clean. Only a
+ *   verified file is renamed to the installer's name.
+ * - The installer waits in a folder the user can write to, so it is checked
+ *   again right before it runs.
+ * =============================================================================
+ */
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::update::{Asset, HttpClient, Release};
+
+/// Checksum files looked for when there is no `<installer>.sha256`.
+const CHECKSUM_LISTS: &[&str] = &["SHA256SUMS", "SHA256SUMS.txt", "checksums.txt"];
+
+const CHUNK: usize = 64 * 1024;
+
+/// How far a download has got.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Progress {
+    pub received: u64,
+    /// Size of the whole file, when the server reported it.
+    pub total: Option<u64>,
+}
+
+impl Progress {
+    /// Completed share, 0.0 to 1.0.
+    pub fn fraction(&self) -> Option<f32> {
+        self.total.filter(|&t| t > 0).map(|t| (self.received as f64 / t as f64).min(1.0) as f32)
+    }
+}
+
+/// The asset holding the checksum of `installer`.
+pub fn checksum_asset<'a>(release: &'a Release, installer: &str) -> Option<&'a Asset> {
+    release.asset(&format!("{}.sha256", installer))
+        .or_else(|| CHECKSUM_LISTS.iter().find_map(|name| release.asset(name)))
+}
+
+/// SHA-256 of `file_name` in a checksum file, in lower case. Lines are
+/// `<hash>  <name>` or `<hash> *<name>`; a bare hash applies to any file.
+pub fn parse_checksum(text: &str, file_name: &str) -> Option<String> {
+    text.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let hash = parts.next()?;
+        let name = parts.next().map(|n| n.trim_start_matches('*'));
+        let valid = hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit());
+        let matches = name.is_none_or(|n| n.eq_ignore_ascii_case(file_name));
+        (valid && matches).then(|| hash.to_ascii_lowercase())
+    })
+}
+
+/// Lower-case hex SHA-256 of the file at `path`.
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; CHUNK];
+    loop {
+        let n = file.read(&mut buf).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Where the bytes of `dest` collect until they are verified.
+pub fn partial_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+/// File name for `installer` of release `tag`, so a partial download is
+/// never resumed with the bytes of another version.
+pub fn installer_file_name(tag: &str, installer: &str) -> String {
+    let tag: String = tag.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect();
+    match installer.rsplit_once('.') {
+        Some((stem, ext)) => format!("{}-{}.{}", stem, tag, ext),
+        None => format!("{}-{}", installer, tag),
+    }
+}
+
+/// A verified installer and the SHA-256 it matched.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Installer {
+    pub path: PathBuf,
+    pub sha256: String,
+}
+
+impl Installer {
+    /// Checks the file against its SHA-256 again. A file that changed since
+    /// the download is deleted.
+    pub fn recheck(&self) -> Result<(), String> {
+        let actual = sha256_file(&self.path)?;
+        if actual.eq_ignore_ascii_case(&self.sha256) {
+            return Ok(());
+        }
+        let _ = fs::remove_file(&self.path);
+        Err(format!(
+            "The installer changed after it was verified: expected SHA-256 {}, got {}. It was deleted; download it again.",
+            self.sha256.to_ascii_lowercase(), actual
+        ))
+    }
+}
+
+/// Downloads `url` to `dest`, resuming a partial download, and verifies it
+/// against `sha256`. `progress` is called as bytes arrive.
+pub fn download(
+    client: &dyn HttpClient,
+    url: &str,
+    dest: &Path,
+    sha256: &str,
+    mut progress: impl FnMut(Progress),
+) -> Result<(), String> {
+    // Verified on an earlier run
+    if dest.exists() && sha256_file(dest)?.eq_ignore_ascii_case(sha256) {
+        return Ok(());
+    }
+
+    let part = partial_path(dest);
+    let mut offset = fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+    let mut stream = client.get_from(url, offset)?;
+    if stream.status == 416 {
+        // Nothing past the partial file, so it is not a prefix of this one
+        offset = 0;
+        stream = client.get_from(url, 0)?;
+    }
+    let file = match stream.status {
+        206 if offset > 0 => OpenOptions::new().append(true).open(&part),
+        200 | 206 => {
+            offset = 0;
+            File::create(&part)
+        }
+        status => return Err(format!("The update server returned HTTP {} for the installer", status)),
+    };
+    let mut file = file.map_err(|e| format!("Failed to write {}: {}", part.display(), e))?;
+
+    let total = stream.length.map(|len| offset + len);
+    let mut received = offset;
+    progress(Progress { received, total });
+    let mut buf = vec![0u8; CHUNK];
+    loop {
+        let n = stream.reader.read(&mut buf).map_err(|e| format!("Download interrupted: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        file.write_all(&buf[..n]).map_err(|e| format!("Failed to write {}: {}", part.display(), e))?;
+        received += n as u64;
+        progress(Progress { received, total });
+    }
+    drop(file);
+    if total.is_some_and(|t| received < t) {
+        return Err(format!("Download interrupted after {} of {} bytes", received, total.unwrap_or(0)));
+    }
+
+    let actual = sha256_file(&part)?;
+    if !actual.eq_ignore_ascii_case(sha256) {
+        let _ = fs::remove_file(&part);
+        return Err(format!(
+            "Checksum mismatch: expected SHA-256 {}, got {}. The download was deleted.",
+            sha256.to_ascii_lowercase(), actual
+        ));
+    }
+    fs::rename(&part, dest).map_err(|e| format!("Failed to save {}: {}", dest.display(), e))
+}
+
+/// Downloads and verifies the `installer` asset of `release` into `dir`.
+pub fn fetch_installer(
+    client: &dyn HttpClient,
+    release: &Release,
+    installer: &str,
+    dir: &Path,
+    progress: impl FnMut(Progress),
+) -> Result<Installer, String> {
+    let url = release.asset_url(installer)
+        .ok_or_else(|| format!("Release {} has no {}", release.tag_name, installer))?;
+    let sums = checksum_asset(release, installer)
+        .ok_or_else(|| format!("Release {} publishes no SHA-256 for {}", release.tag_name, installer))?;
+    let sha256 = parse_checksum(&client.get(&sums.browser_download_url, &[])?.into_body()?, installer)
+        .ok_or_else(|| format!("{} has no SHA-256 for {}", sums.name, installer))?;
+
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let dest = dir.join(installer_file_name(&release.tag_name, installer));
+    download(client, url, &dest, &sha256, progress)?;
+    Ok(Installer { path: dest, sha256 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Reply, Server};
+    use crate::update::UreqClient;
+
+    const INSTALLER: &str = "WinhiderInstaller.exe";
+
+    /// How the stub answers requests for the installer.
+    #[derive(Clone, Copy)]
+    enum Serve {
+        /// Honour `Range` with 206.
+        Ranges,
+        /// Ignore `Range` and send the whole file.
+        Whole,
+        /// Promise the whole file but hang up after this many bytes.
+        CutAt(usize),
+    }
+
+    /// Answers `count` requests: `/WinhiderInstaller.exe` with `payload`
+    /// as the next of `serve` says, and the `.sha256` asset with `sums`.
+    fn stub_server(count: usize, payload: Vec<u8>, sums: String, serve: Vec<Serve>) -> Server {
+        let mut serve = serve.into_iter();
+        Server::start(count, move |request| {
+            if request.line.contains(".sha256") {
+                return Reply::new(200, sums.clone());
+            }
+            let range = request.header("Range")
+                .and_then(|r| r.strip_prefix("bytes=")?.trim_end_matches('-').parse::<usize>().ok());
+            match (serve.next().unwrap_or(Serve::Ranges), range) {
+                (Serve::Ranges, Some(start)) if start >= payload.len() => {
+                    Reply::new(416, "").header("Content-Range", format!("bytes */{}", payload.len()))
+                }
+                (Serve::Ranges, Some(start)) => Reply::new(206, &payload[start..])
+                    .header("Content-Range", format!("bytes {}-{}/{}", start, payload.len() - 1, payload.len())),
+                (Serve::CutAt(n), _) => Reply::new(200, &payload[..n]).header("Content-Length", payload.len()),
+                _ => Reply::new(200, payload.clone()),
+            }
+        })
+    }
+
+    fn payload() -> Vec<u8> {
+        (0..200_000u32).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    fn sha256_of(bytes: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(bytes))
+    }
+
+    fn release(base: &str) -> Release {
+        let asset = |name: &str| Asset { name: name.to_string(), browser_download_url: format!("{}/{}", base, name) };
+        Release {
+            tag_name: "v1.2.1".to_string(),
+            assets: vec![asset(INSTALLER), asset(&format!("{}.sha256", INSTALLER))],
+            ..Default::default()
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("winhider-download-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn downloads_and_verifies_the_installer() {
+        let data = payload();
+        let sums = format!("{}  {}\n", sha256_of(&data), INSTALLER);
+        let server = stub_server(2, data.clone(), sums, vec![Serve::Ranges]);
+        let dir = temp_dir("fresh");
+
+        let mut updates = Vec::new();
+        let installer = fetch_installer(&UreqClient::new("test"), &release(&server.base), INSTALLER, &dir, |p| updates.push(p)).unwrap();
+        let path = &installer.path;
+
+        assert_eq!(installer, Installer { path: dir.join("WinhiderInstaller-v1.2.1.exe"), sha256: sha256_of(&data) });
+        assert_eq!(fs::read(path).unwrap(), data);
+        assert!(!partial_path(path).exists());
+        assert_eq!(updates.first(), Some(&Progress { received: 0, total: Some(data.len() as u64) }));
+        assert_eq!(updates.last().and_then(|p| p.fraction()), Some(1.0));
+        let seen = server.join();
+        assert_eq!(seen[0].line, "GET /WinhiderInstaller.exe.sha256 HTTP/1.1");
+        assert_eq!((seen[1].line.as_str(), seen[1].header("Range")), ("GET /WinhiderInstaller.exe HTTP/1.1", None));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn interrupted_download_resumes_with_a_range_request() {
+        let data = payload();
+        let sums = format!("{} *{}\n", sha256_of(&data), INSTALLER);
+        let server = stub_server(4, data.clone(), sums, vec![Serve::CutAt(70_000), Serve::Ranges]);
+        let dir = temp_dir("resume");
+        let client = UreqClient::new("test");
+
+        let err = fetch_installer(&client, &release(&server.base), INSTALLER, &dir, |_| {}).unwrap_err();
+        assert!(err.starts_with("Download interrupted"), "{}", err);
+        let part = partial_path(&dir.join("WinhiderInstaller-v1.2.1.exe"));
+        assert_eq!(fs::metadata(&part).unwrap().len(), 70_000);
+
+        let mut first = None;
+        let path = fetch_installer(&client, &release(&server.base), INSTALLER, &dir, |p| { first.get_or_insert(p); }).unwrap().path;
+        assert_eq!(fs::read(&path).unwrap(), data);
+        assert_eq!(first, Some(Progress { received: 70_000, total: Some(data.len() as u64) }));
+        let seen = server.join();
+        assert_eq!(seen[3].header("Range"), Some("bytes=70000-"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn server_without_ranges_restarts_the_download() {
+        let data = payload();
+        let sums = sha256_of(&data);
+        let server = stub_server(2, data.clone(), sums, vec![Serve::Whole]);
+        let dir = temp_dir("no-ranges");
+        let dest = dir.join("WinhiderInstaller-v1.2.1.exe");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(partial_path(&dest), &data[..1000]).unwrap();
+
+        let path = fetch_installer(&UreqClient::new("test"), &release(&server.base), INSTALLER, &dir, |_| {}).unwrap().path;
+        assert_eq!(fs::read(path).unwrap(), data);
+        server.join();
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn stale_partial_beyond_the_end_is_replaced() {
+        let data = payload();
+        let server = stub_server(3, data.clone(), sha256_of(&data), vec![Serve::Ranges, Serve::Ranges]);
+        let dir = temp_dir("stale");
+        let dest = dir.join("WinhiderInstaller-v1.2.1.exe");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(partial_path(&dest), vec![0u8; data.len() + 10]).unwrap();
+
+        let path = fetch_installer(&UreqClient::new("test"), &release(&server.base), INSTALLER, &dir, |_| {}).unwrap().path;
+        assert_eq!(fs::read(path).unwrap(), data);
+        server.join();
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn checksum_mismatch_is_refused_and_deleted() {
+        let data = payload();
+        let sums = format!("{}  {}\n", sha256_of(b"something else"), INSTALLER);
+        let server = stub_server(2, data, sums, vec![Serve::Ranges]);
+        let dir = temp_dir("mismatch");
+
+        let err = fetch_installer(&UreqClient::new("test"), &release(&server.base), INSTALLER, &dir, |_| {}).unwrap_err();
+        assert!(err.starts_with("Checksum mismatch: expected SHA-256"), "{}", err);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        server.join();
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn installer_changed_after_the_download_fails_the_recheck() {
+        let dir = temp_dir("recheck");
+        fs::create_dir_all(&dir).unwrap();
+        let installer = Installer { path: dir.join(INSTALLER), sha256: sha256_of(b"installer").to_uppercase() };
+        fs::write(&installer.path, b"installer").unwrap();
+        assert_eq!(installer.recheck(), Ok(()));
+
+        fs::write(&installer.path, b"replaced").unwrap();
+        let err = installer.recheck().unwrap_err();
+        assert!(err.starts_with("The installer changed after it was verified"), "{}", err);
+        assert!(!installer.path.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn release_without_a_checksum_is_refused() {
+        let mut release = release("http://127.0.0.1:9");
+        release.assets.truncate(1);
+        let err = fetch_installer(&UreqClient::new("test"), &release, INSTALLER, &temp_dir("unused"), |_| {}).unwrap_err();
+        assert_eq!(err, "Release v1.2.1 publishes no SHA-256 for WinhiderInstaller.exe");
+    }
+
+    #[test]
+    fn checksum_lists_are_parsed() {
+        let hash = "A".repeat(64);
+        let list = format!("{}  WinhiderPortable.zip\n{} *winhiderinstaller.exe\n", "b".repeat(64), hash);
+        assert_eq!(parse_checksum(&list, INSTALLER), Some("a".repeat(64)));
+        assert_eq!(parse_checksum(&format!("{}\n", hash), INSTALLER), Some("a".repeat(64)));
+        assert_eq!(parse_checksum("abc123  WinhiderInstaller.exe", INSTALLER), None);
+        assert_eq!(parse_checksum(&list, "Other.exe"), None);
+        assert_eq!(parse_checksum("", INSTALLER), None);
+    }
+
+    #[test]
+    fn sums_file_is_used_when_there_is_no_sha256_asset() {
+        let asset = |name: &str| Asset { name: name.to_string(), browser_download_url: String::new() };
+        let mut release = Release { assets: vec![asset(INSTALLER), asset("SHA256SUMS")], ..Default::default() };
+        assert_eq!(checksum_asset(&release, INSTALLER).map(|a| a.name.as_str()), Some("SHA256SUMS"));
+        release.assets.push(asset("WinhiderInstaller.exe.sha256"));
+        assert_eq!(checksum_asset(&release, INSTALLER).map(|a| a.name.as_str()), Some("WinhiderInstaller.exe.sha256"));
+    }
+
+    #[test]
+    fn known_digest_and_names() {
+        let dir = temp_dir("digest");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("abc"), b"abc").unwrap();
+        assert_eq!(sha256_file(&dir.join("abc")).unwrap(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(installer_file_name("v1.3.0-beta.1+build/5", INSTALLER), "WinhiderInstaller-v1.3.0-beta.1_build_5.exe");
+        assert_eq!(partial_path(Path::new("a/b.exe")), Path::new("a/b.exe.part"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,303 @@
+/*
+ * =============================================================================
+ * WinHider Downscale - Preview Frame Scaling
+ * =============================================================================
+ *
+ * Filename: downscale.rs
+ * Description: Shrinks tightly packed 8-bit RGBA frames to a target resolution
+ *              for the live preview. The preview quality setting picks both the
+ *              resolution and the filter.
+ *
+ * Filters:
+ * - Nearest:  one source sample per pixel; fastest, aliases on fine detail.
+ * - Bilinear: blends the four nearest samples; smooth for moderate ratios.
+ * - Box:      averages every source pixel under the output pixel; best for
+ *             large reductions such as 4K down to a small pane.
+ *
+ * Never upscales and keeps the source aspect ratio. Self-contained so the
+ * benchmark in `benches/downscale.rs` can include it directly.
+ * =============================================================================
+ */
+
+const CHANNELS: usize = 4;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Filter {
+    Nearest,
+    Box,
+    Bilinear,
+}
+
+/// Output bounds and filter for a `preview_quality` setting
+/// (1 = Low, 2 = Medium, 3 = High).
+pub fn quality_params(preview_quality: u32) -> (u32, u32, Filter) {
+    match preview_quality {
+        0 | 1 => (640, 360, Filter::Nearest),
+        2 => (960, 540, Filter::Bilinear),
+        _ => (1280, 720, Filter::Box),
+    }
+}
+
+/// Largest size that fits in `max_w` x `max_h` with the aspect ratio of
+/// `src_w` x `src_h`. Never larger than the source, never zero.
+pub fn fit_size(src_w: u32, src_h: u32, max_w: u32, max_h: u32) -> (u32, u32) {
+    if src_w == 0 || src_h == 0 {
+        return (0, 0);
+    }
+    if src_w <= max_w && src_h <= max_h {
+        return (src_w, src_h);
+    }
+    // Scale by the tighter bound, in integer math to avoid rounding past it
+    let (w, h) = if (max_w as u64) * (src_h as u64) <= (max_h as u64) * (src_w as u64) {
+        (max_w as u64, (src_h as u64 * max_w as u64) / src_w as u64)
+    } else {
+        ((src_w as u64 * max_h as u64) / src_h as u64, max_h as u64)
+    };
+    ((w as u32).max(1), (h as u32).max(1))
+}
+
+/// Scales `src` (`src_w` x `src_h`, RGBA, no row padding) to `dst_w` x `dst_h`.
+pub fn downscale(src: &[u8], src_w: u32, src_h: u32, dst_w: u32, dst_h: u32, filter: Filter) -> Result<Vec<u8>, String> {
+    if src_w == 0 || src_h == 0 || dst_w == 0 || dst_h == 0 {
+        return Err("Frame size must not be zero".to_string());
+    }
+    let expected = src_w as usize * src_h as usize * CHANNELS;
+    if src.len() < expected {
+        return Err(format!("Frame buffer too small: {} bytes for {}x{}", src.len(), src_w, src_h));
+    }
+    if dst_w > src_w || dst_h > src_h {
+        return Err("Downscale target is larger than the source".to_string());
+    }
+    if dst_w == src_w && dst_h == src_h {
+        return Ok(src[..expected].to_vec());
+    }
+
+    let src = Image { data: &src[..expected], width: src_w as usize, height: src_h as usize };
+    let (dst_w, dst_h) = (dst_w as usize, dst_h as usize);
+    Ok(match filter {
+        Filter::Nearest => nearest(&src, dst_w, dst_h),
+        Filter::Box => box_average(&src, dst_w, dst_h),
+        Filter::Bilinear => bilinear(&src, dst_w, dst_h),
+    })
+}
+
+struct Image<'a> {
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+}
+
+impl Image<'_> {
+    fn pixel(&self, x: usize, y: usize) -> &[u8] {
+        let i = (y * self.width + x) * CHANNELS;
+        &self.data[i..i + CHANNELS]
+    }
+}
+
+/// Source index sampled by output index `i` (pixel centres aligned).
+fn centre_index(i: usize, src_len: usize, dst_len: usize) -> usize {
+    ((2 * i + 1) * src_len / (2 * dst_len)).min(src_len - 1)
+}
+
+fn nearest(src: &Image, dst_w: usize, dst_h: usize) -> Vec<u8> {
+    let xs: Vec<usize> = (0..dst_w).map(|x| centre_index(x, src.width, dst_w)).collect();
+    let mut out = Vec::with_capacity(dst_w * dst_h * CHANNELS);
+    for y in 0..dst_h {
+        let sy = centre_index(y, src.height, dst_h);
+        for &sx in &xs {
+            out.extend_from_slice(src.pixel(sx, sy));
+        }
+    }
+    out
+}
+
+/// Half-open source span `[start, end)` covered by output index `i`.
+fn span(i: usize, src_len: usize, dst_len: usize) -> (usize, usize) {
+    let start = i * src_len / dst_len;
+    let end = ((i + 1) * src_len / dst_len).max(start + 1).min(src_len);
+    (start, end)
+}
+
+fn box_average(src: &Image, dst_w: usize, dst_h: usize) -> Vec<u8> {
+    let x_spans: Vec<(usize, usize)> = (0..dst_w).map(|x| span(x, src.width, dst_w)).collect();
+    let mut out = Vec::with_capacity(dst_w * dst_h * CHANNELS);
+    for y in 0..dst_h {
+        let (y0, y1) = span(y, src.height, dst_h);
+        for &(x0, x1) in &x_spans {
+            let mut sum = [0u32; CHANNELS];
+            for sy in y0..y1 {
+                let row_start = (sy * src.width + x0) * CHANNELS;
+                let row = &src.data[row_start..row_start + (x1 - x0) * CHANNELS];
+                for px in row.chunks_exact(CHANNELS) {
+                    for (acc, &v) in sum.iter_mut().zip(px) {
+                        *acc += v as u32;
+                    }
+                }
+            }
+            let count = ((y1 - y0) * (x1 - x0)) as u32;
+            // Rounded division
+            out.extend(sum.iter().map(|&s| ((s + count / 2) / count) as u8));
+        }
+    }
+    out
+}
+
+/// Fixed-point scale for bilinear weights (8 fractional bits).
+const WEIGHT_ONE: u32 = 256;
+
+/// Left sample, right sample and fixed-point weight of the right one for
+/// output index `i`.
+fn bilinear_taps(i: usize, src_len: usize, dst_len: usize) -> (usize, usize, u32) {
+    let pos = ((i as f32 + 0.5) * src_len as f32 / dst_len as f32 - 0.5).max(0.0);
+    let left = (pos as usize).min(src_len - 1);
+    let right = (left + 1).min(src_len - 1);
+    let weight = ((pos - left as f32) * WEIGHT_ONE as f32).round() as u32;
+    (left, right, weight.min(WEIGHT_ONE))
+}
+
+fn bilinear(src: &Image, dst_w: usize, dst_h: usize) -> Vec<u8> {
+    let x_taps: Vec<(usize, usize, u32)> = (0..dst_w).map(|x| bilinear_taps(x, src.width, dst_w)).collect();
+    let mut out = Vec::with_capacity(dst_w * dst_h * CHANNELS);
+    for y in 0..dst_h {
+        let (y0, y1, wy) = bilinear_taps(y, src.height, dst_h);
+        for &(x0, x1, wx) in &x_taps {
+            let (a, b) = (src.pixel(x0, y0), src.pixel(x1, y0));
+            let (c, d) = (src.pixel(x0, y1), src.pixel(x1, y1));
+            for ch in 0..CHANNELS {
+                let top = a[ch] as u32 * (WEIGHT_ONE - wx) + b[ch] as u32 * wx;
+                let bottom = c[ch] as u32 * (WEIGHT_ONE - wx) + d[ch] as u32 * wx;
+                let value = top * (WEIGHT_ONE - wy) + bottom * wy;
+                // Round, then drop both sets of fractional bits
+                out.push(((value + WEIGHT_ONE * WEIGHT_ONE / 2) / (WEIGHT_ONE * WEIGHT_ONE)) as u8);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILTERS: [Filter; 3] = [Filter::Nearest, Filter::Box, Filter::Bilinear];
+
+    fn solid(w: u32, h: u32, rgba: [u8; 4]) -> Vec<u8> {
+        rgba.iter().copied().cycle().take((w * h * 4) as usize).collect()
+    }
+
+    /// Red encodes x, green encodes y.
+    fn gradient(w: u32, h: u32) -> Vec<u8> {
+        let mut data = Vec::with_capacity((w * h * 4) as usize);
+        for y in 0..h {
+            for x in 0..w {
+                data.extend_from_slice(&[x as u8, y as u8, 0, 255]);
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn fit_keeps_aspect_ratio() {
+        assert_eq!(fit_size(1920, 1080, 960, 540), (960, 540));
+        assert_eq!(fit_size(3840, 2160, 1280, 720), (1280, 720));
+        // Portrait monitor is bounded by height
+        assert_eq!(fit_size(1080, 1920, 960, 540), (303, 540));
+        // Ultrawide is bounded by width
+        assert_eq!(fit_size(3440, 1440, 960, 540), (960, 401));
+    }
+
+    #[test]
+    fn fit_never_upscales_or_returns_zero() {
+        assert_eq!(fit_size(800, 600, 1280, 720), (800, 600));
+        assert_eq!(fit_size(10000, 1, 100, 100), (100, 1));
+        assert_eq!(fit_size(0, 100, 100, 100), (0, 0));
+    }
+
+    #[test]
+    fn quality_levels_grow_with_setting() {
+        let (low, _, _) = quality_params(1);
+        let (medium, _, _) = quality_params(2);
+        let (high, _, _) = quality_params(3);
+        assert!(low < medium && medium < high);
+    }
+
+    #[test]
+    fn output_has_requested_size() {
+        let src = gradient(37, 23);
+        for filter in FILTERS {
+            let out = downscale(&src, 37, 23, 11, 7, filter).unwrap();
+            assert_eq!(out.len(), 11 * 7 * 4, "{:?}", filter);
+        }
+    }
+
+    #[test]
+    fn solid_colour_stays_solid() {
+        let src = solid(64, 48, [10, 200, 30, 255]);
+        for filter in FILTERS {
+            let out = downscale(&src, 64, 48, 13, 9, filter).unwrap();
+            assert!(out.chunks(4).all(|p| p == [10, 200, 30, 255]), "{:?}", filter);
+        }
+    }
+
+    #[test]
+    fn same_size_is_a_copy() {
+        let src = gradient(5, 3);
+        for filter in FILTERS {
+            assert_eq!(downscale(&src, 5, 3, 5, 3, filter).unwrap(), src);
+        }
+    }
+
+    #[test]
+    fn nearest_samples_block_centres() {
+        // 4x1 -> 2x1 picks source columns 1 and 3
+        let src = gradient(4, 1);
+        let out = downscale(&src, 4, 1, 2, 1, Filter::Nearest).unwrap();
+        assert_eq!(out, vec![1, 0, 0, 255, 3, 0, 0, 255]);
+    }
+
+    #[test]
+    fn box_averages_each_block() {
+        // 2x2 -> 1x1: mean of 0,1 in x and 0,1 in y, rounded
+        let src = gradient(2, 2);
+        let out = downscale(&src, 2, 2, 1, 1, Filter::Box).unwrap();
+        assert_eq!(out, vec![1, 1, 0, 255]);
+
+        let src = vec![0, 0, 0, 0, 255, 255, 255, 255];
+        let out = downscale(&src, 2, 1, 1, 1, Filter::Box).unwrap();
+        assert_eq!(out, vec![128, 128, 128, 128]);
+    }
+
+    #[test]
+    fn bilinear_interpolates_between_samples() {
+        // 4x1 -> 2x1 samples halfway between columns 0/1 and 2/3
+        let src = vec![0, 0, 0, 255, 100, 0, 0, 255, 200, 0, 0, 255, 250, 0, 0, 255];
+        let out = downscale(&src, 4, 1, 2, 1, Filter::Bilinear).unwrap();
+        assert_eq!(out, vec![50, 0, 0, 255, 225, 0, 0, 255]);
+    }
+
+    #[test]
+    fn gradient_stays_monotonic() {
+        let src = gradient(200, 3);
+        for filter in FILTERS {
+            let out = downscale(&src, 200, 3, 33, 1, filter).unwrap();
+            let reds: Vec<u8> = out.chunks(4).map(|p| p[0]).collect();
+            assert!(reds.windows(2).all(|w| w[0] <= w[1]), "{:?}: {:?}", filter, reds);
+        }
+    }
+
+    #[test]
+    fn odd_sizes_reach_the_last_row_and_column() {
+        let src = gradient(7, 5);
+        let out = downscale(&src, 7, 5, 3, 2, Filter::Box).unwrap();
+        // Last output pixel averages columns 4..7 and rows 2..5
+        assert_eq!(&out[out.len() - 4..], &[5, 3, 0, 255]);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let src = gradient(4, 4);
+        assert!(downscale(&src[..10], 4, 4, 2, 2, Filter::Box).is_err());
+        assert!(downscale(&src, 4, 4, 8, 8, Filter::Box).is_err());
+        assert!(downscale(&src, 4, 4, 0, 2, Filter::Box).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+/*
+ * =============================================================================
+ * WinHider Dry Run - Current vs Intended State
+ * =============================================================================
+ *
+ * Filename: dryrun.rs
+ * Description: Works out which windows a set of queued tasks would change,
+ *              without touching anything. The result is a per-window diff of
+ *              current and intended capture/taskbar visibility, shown in the
+ *              GUI preview dialog and printed as JSON by `--dry-run`.
+ *
+ * Notes:
+ * - Uses the same planner and strategy choice as a real run, so an injected
+ *   action covers every window of the process while an external call only
+ *   touches the windows that asked for it.
+ * - Windows whose state would not change are left out of the diff.
+ * =============================================================================
+ */
+
+use serde::Serialize;
+use windows::Win32::Foundation::HWND;
+
+use crate::planner::{self, InjectionTask, Strategy};
+use crate::InjectionAction;
+
+/// Visibility of one window as it is right now.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct WindowState {
+    pub hwnd: HWND,
+    pub pid: u32,
+    pub title: String,
+    pub capture_hidden: bool,
+    pub taskbar_hidden: bool,
+}
+
+/// `true` means hidden.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+pub struct StateChange {
+    pub current: bool,
+    pub intended: bool,
+    pub strategy: Strategy,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct WindowDiff {
+    pub hwnd: isize,
+    pub pid: u32,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capture: Option<StateChange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub taskbar: Option<StateChange>,
+}
+
+/// Intended hidden state and the strategy that gets it there.
+type Planned = Option<(bool, Strategy)>;
+
+/// Diff of what running `tasks` would do to `windows`. `own_pid` is the
+/// WinHider process, which never needs an injection.
+pub fn diff(windows: &[WindowState], tasks: &[InjectionTask], prefer_external: bool, own_pid: u32) -> Vec<WindowDiff> {
+    // (capture, taskbar) intended state per window, in `windows` order
+    let mut intended: Vec<(Planned, Planned)> = vec![(None, None); windows.len()];
+
+    for injection in planner::plan(tasks) {
+        for &action in &injection.actions {
+            let strategy = planner::choose_strategy(action, injection.pid == own_pid, prefer_external);
+            let is_capture = matches!(action, InjectionAction::HideCapture | InjectionAction::ShowCapture);
+            let external = planner::window_actions(&injection.tasks, action);
+
+            for (i, w) in windows.iter().enumerate() {
+                let action = match strategy {
+                    // The payload walks every window of the process
+                    Strategy::Injection if w.pid == injection.pid => action,
+                    // Each window gets its own last request
+                    Strategy::External => match external.iter().find(|(hwnd, _)| *hwnd == w.hwnd) {
+                        Some(&(_, action)) => action,
+                        None => continue,
+                    },
+                    _ => continue,
+                };
+                let hide = matches!(action, InjectionAction::HideCapture | InjectionAction::HideTaskbar);
+                if is_capture {
+                    intended[i].0 = Some((hide, strategy));
+                } else {
+                    intended[i].1 = Some((hide, strategy));
+                }
+            }
+        }
+    }
+
+    let change = |current: bool, planned: Planned| {
+        planned
+            .filter(|(hide, _)| *hide != current)
+            .map(|(hide, strategy)| StateChange { current, intended: hide, strategy })
+    };
+
+    windows.iter()
+        .zip(intended)
+        .filter_map(|(w, (capture, taskbar))| {
+            let capture = change(w.capture_hidden, capture);
+            let taskbar = change(w.taskbar_hidden, taskbar);
+            if capture.is_none() && taskbar.is_none() {
+                return None;
+            }
+            Some(WindowDiff { hwnd: w.hwnd.0, pid: w.pid, title: w.title.clone(), capture, taskbar })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use InjectionAction::*;
+
+    const OWN_PID: u32 = 1;
+
+    fn window(hwnd: isize, pid: u32, capture_hidden: bool, taskbar_hidden: bool) -> WindowState {
+        WindowState { hwnd: HWND(hwnd), pid, title: format!("w{}", hwnd), capture_hidden, taskbar_hidden }
+    }
+
+    fn task(hwnd: isize, pid: u32, action: InjectionAction) -> InjectionTask {
+        InjectionTask { hwnd: HWND(hwnd), pid, action }
+    }
+
+    #[test]
+    fn no_tasks_no_changes() {
+        let windows = [window(10, 5, false, false)];
+        assert!(diff(&windows, &[], true, OWN_PID).is_empty());
+    }
+
+    #[test]
+    fn injected_capture_covers_every_window_of_the_process() {
+        let windows = [window(10, 5, false, false), window(11, 5, false, false), window(20, 6, false, false)];
+        let d = diff(&windows, &[task(10, 5, HideCapture)], true, OWN_PID);
+        let hwnds: Vec<isize> = d.iter().map(|w| w.hwnd).collect();
+        assert_eq!(hwnds, vec![10, 11]);
+        assert_eq!(d[0].capture, Some(StateChange { current: false, intended: true, strategy: Strategy::Injection }));
+        assert_eq!(d[0].taskbar, None);
+    }
+
+    #[test]
+    fn external_taskbar_only_touches_requested_windows() {
+        let windows = [window(10, 5, false, false), window(11, 5, false, false)];
+        let d = diff(&windows, &[task(11, 5, HideTaskbar)], true, OWN_PID);
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].hwnd, 11);
+        assert_eq!(d[0].taskbar, Some(StateChange { current: false, intended: true, strategy: Strategy::External }));
+    }
+
+    #[test]
+    fn external_windows_of_one_process_keep_their_own_action() {
+        let windows = [window(10, 5, false, true), window(11, 5, false, false)];
+        let d = diff(&windows, &[task(10, 5, ShowTaskbar), task(11, 5, HideTaskbar)], true, OWN_PID);
+        assert_eq!(d.len(), 2);
+        assert_eq!(d[0].taskbar, Some(StateChange { current: true, intended: false, strategy: Strategy::External }));
+        assert_eq!(d[1].taskbar, Some(StateChange { current: false, intended: true, strategy: Strategy::External }));
+    }
+
+    #[test]
+    fn injected_taskbar_without_preference_covers_the_process() {
+        let windows = [window(10, 5, false, false), window(11, 5, false, false)];
+        let d = diff(&windows, &[task(11, 5, HideTaskbar)], false, OWN_PID);
+        assert_eq!(d.len(), 2);
+        assert!(d.iter().all(|w| w.taskbar.unwrap().strategy == Strategy::Injection));
+    }
+
+    #[test]
+    fn already_in_intended_state_is_omitted() {
+        let windows = [window(10, 5, true, false), window(11, 5, false, false)];
+        let d = diff(&windows, &[task(10, 5, HideCapture)], true, OWN_PID);
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].hwnd, 11);
+    }
+
+    #[test]
+    fn show_reports_hidden_to_visible() {
+        let windows = [window(10, 5, true, true)];
+        let d = diff(&windows, &[task(10, 5, ShowCapture), task(10, 5, ShowTaskbar)], true, OWN_PID);
+        assert_eq!(d[0].capture, Some(StateChange { current: true, intended: false, strategy: Strategy::Injection }));
+        assert_eq!(d[0].taskbar, Some(StateChange { current: true, intended: false, strategy: Strategy::External }));
+    }
+
+    #[test]
+    fn own_windows_use_external_calls() {
+        let windows = [window(10, OWN_PID, false, false), window(11, OWN_PID, false, false)];
+        let d = diff(&windows, &[task(10, OWN_PID, HideCapture)], false, OWN_PID);
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].capture.unwrap().strategy, Strategy::External);
+    }
+
+    #[test]
+    fn serializes_to_json() {
+        let windows = [window(10, 5, false, true)];
+        let d = diff(&windows, &[task(10, 5, HideCapture)], true, OWN_PID);
+        let json = serde_json::to_value(&d).unwrap();
+        assert_eq!(json[0]["hwnd"], 10);
+        assert_eq!(json[0]["pid"], 5);
+        assert_eq!(json[0]["title"], "w10");
+        assert_eq!(json[0]["capture"]["current"], false);
+        assert_eq!(json[0]["capture"]["intended"], true);
+        assert_eq!(json[0]["capture"]["strategy"], "injection");
+        assert!(json[0].get("taskbar").is_none());
+    }
+}
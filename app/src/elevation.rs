@@ -0,0 +1,180 @@
+/*
+ * =============================================================================
+ * WinHider Elevation - Token Integrity Helpers
+ * =============================================================================
+ *
+ * Filename: elevation.rs
+ * Description: Reads the integrity level and elevation state of process tokens
+ *              so windows that cannot be modified from the current token can
+ *              be flagged before any injection is attempted. Also relaunches
+ *              WinHider through the UAC "runas" verb on request.
+ *
+ * Notes:
+ * - A process at a higher integrity level than ours cannot be opened for
+ *   injection, and UIPI blocks external style changes on its windows.
+ * - Tokens of elevated processes are often unreadable from a medium token;
+ *   an unreadable token is treated as "requires admin" when we are not
+ *   elevated ourselves.
+ * =============================================================================
+ */
+
+use std::ffi::c_void;
+
+use windows::core::{w, HSTRING, PCWSTR};
+use windows::Win32::Foundation::*;
+use windows::Win32::Security::*;
+use windows::Win32::System::Threading::*;
+use windows::Win32::UI::Shell::ShellExecuteW;
+use windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+
+/// Error text returned when a target is out of reach from the current token.
+pub const ELEVATION_REQUIRED: &str = "Target runs elevated - relaunch as administrator to modify it";
+
+// Mandatory label RIDs (winnt.h)
+const LOW_RID: u32 = 0x1000;
+const MEDIUM_RID: u32 = 0x2000;
+const HIGH_RID: u32 = 0x3000;
+const SYSTEM_RID: u32 = 0x4000;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum IntegrityLevel {
+    Untrusted,
+    Low,
+    Medium,
+    High,
+    System,
+}
+
+impl IntegrityLevel {
+    fn from_rid(rid: u32) -> Self {
+        match rid {
+            r if r >= SYSTEM_RID => Self::System,
+            r if r >= HIGH_RID => Self::High,
+            r if r >= MEDIUM_RID => Self::Medium,
+            r if r >= LOW_RID => Self::Low,
+            _ => Self::Untrusted,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Untrusted => "Untrusted",
+            Self::Low => "Low",
+            Self::Medium => "Medium",
+            Self::High => "High",
+            Self::System => "System",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TokenInfo {
+    pub integrity: IntegrityLevel,
+    pub elevated: bool,
+}
+
+impl TokenInfo {
+    pub fn label(&self) -> String {
+        if self.elevated {
+            format!("{}, elevated", self.integrity.label())
+        } else {
+            self.integrity.label().to_string()
+        }
+    }
+}
+
+/// Reads the token of another process. Returns `None` when the token cannot
+/// be opened from the current security context.
+pub fn query_process_token(pid: u32) -> Option<TokenInfo> {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let info = query_token(process);
+        let _ = CloseHandle(process);
+        info
+    }
+}
+
+/// Token of the running WinHider process.
+pub fn current_token() -> TokenInfo {
+    unsafe { query_token(GetCurrentProcess()) }.unwrap_or(TokenInfo {
+        integrity: IntegrityLevel::Medium,
+        elevated: false,
+    })
+}
+
+/// True when `target` cannot be modified from `current` but could be from an
+/// elevated WinHider.
+pub fn requires_admin(current: &TokenInfo, target: Option<&TokenInfo>) -> bool {
+    if current.elevated {
+        return false;
+    }
+    match target {
+        Some(target) => target.integrity > current.integrity,
+        None => true,
+    }
+}
+
+/// Convenience wrapper used right before touching a process.
+pub fn pid_requires_admin(pid: u32) -> bool {
+    requires_admin(&current_token(), query_process_token(pid).as_ref())
+}
+
+/// Starts a new elevated instance of the current executable with `args`.
+/// The caller is expected to exit once this returns `Ok`.
+pub fn relaunch_elevated(args: &str) -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to get current exe path: {}", e))?;
+    let file = HSTRING::from(exe.as_path());
+    let params = HSTRING::from(args);
+
+    let result = unsafe { ShellExecuteW(HWND(0), w!("runas"), &file, &params, PCWSTR::null(), SW_SHOWNORMAL) };
+
+    // ShellExecuteW returns a value greater than 32 on success
+    if result.0 <= 32 {
+        return Err(format!("Elevation cancelled or failed (code {})", result.0));
+    }
+    Ok(())
+}
+
+unsafe fn query_token(process: HANDLE) -> Option<TokenInfo> {
+    unsafe {
+        let mut token = HANDLE::default();
+        OpenProcessToken(process, TOKEN_QUERY, &mut token).ok()?;
+
+        let mut elevation = TOKEN_ELEVATION::default();
+        let mut len = 0u32;
+        let elevated = GetTokenInformation(
+            token,
+            TokenElevation,
+            Some(&mut elevation as *mut _ as *mut c_void),
+            std::mem::size_of::<TOKEN_ELEVATION>() as u32,
+            &mut len,
+        )
+        .is_ok()
+            && elevation.TokenIsElevated != 0;
+
+        // The integrity label is variable sized: TOKEN_MANDATORY_LABEL followed by its SID.
+        // First call only reports the required size.
+        let _ = GetTokenInformation(token, TokenIntegrityLevel, None, 0, &mut len);
+        let mut buffer = vec![0u64; (len as usize).div_ceil(8).max(1)];
+        let integrity = if GetTokenInformation(
+            token,
+            TokenIntegrityLevel,
+            Some(buffer.as_mut_ptr() as *mut c_void),
+            (buffer.len() * 8) as u32,
+            &mut len,
+        )
+        .is_ok()
+        {
+            let label = &*(buffer.as_ptr() as *const TOKEN_MANDATORY_LABEL);
+            let count = *GetSidSubAuthorityCount(label.Label.Sid);
+            let rid = *GetSidSubAuthority(label.Label.Sid, count.saturating_sub(1) as u32);
+            Some(IntegrityLevel::from_rid(rid))
+        } else {
+            None
+        };
+
+        let _ = CloseHandle(token);
+
+        integrity.map(|integrity| TokenInfo { integrity, elevated })
+    }
+}
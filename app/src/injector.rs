@@ -0,0 +1,457 @@
+/*
+ * =============================================================================
+ * WinHider Injector - Background Injection Jobs
+ * =============================================================================
+ *
+ * Filename: injector.rs
+ * Description: Runs payload injections on a small worker pool so the UI never
+ *              waits on `inject_payload` (which can block for seconds per
+ *              call). Callers submit a job made of per-window tasks, follow
+ *              its progress through a `JobHandle` and receive results as
+ *              `InjectionEvent`s over a channel.
+ *
+ * Notes:
+ * - Jobs are batched by the planner: one injection per target process,
+ *   carrying every action requested for it. Progress counts injections.
+ * - Each action runs with the strategy picked by the planner; actions that
+ *   work from outside the target skip the injection entirely.
+ * - Cancelling a job skips every injection that has not started yet; an
+ *   injection already in flight always runs to completion.
+ * - Every worker has its own queue and a process always goes to the same
+ *   one, so injections into one process run in the order they were
+ *   submitted, even across jobs. Hide then show in quick succession can
+ *   never finish the other way round.
+ * - The CLI uses the same API and simply blocks on `recv`.
+ * =============================================================================
+ */
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crossbeam_channel::{Receiver, Sender};
+use windows::Win32::Foundation::HWND;
+
+use crate::planner::{self, InjectionTask, PlannedInjection, Strategy};
+use crate::{inject_payload, set_capture_self, set_taskbar_visibility_external, InjectionAction};
+
+pub enum TaskOutcome {
+    Done,
+    Failed(String),
+    /// Skipped because the job was cancelled first.
+    Cancelled,
+}
+
+pub struct TaskResult {
+    pub injection: PlannedInjection,
+    pub outcome: TaskOutcome,
+    /// Actions that took effect and how, even when a later step failed.
+    pub applied: Vec<(InjectionAction, Strategy)>,
+}
+
+pub struct JobSummary {
+    pub job_id: u64,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    /// Actions applied per strategy across the whole job.
+    pub external_actions: usize,
+    pub injected_actions: usize,
+}
+
+pub enum InjectionEvent {
+    Task(TaskResult),
+    Finished(JobSummary),
+}
+
+struct JobState {
+    id: u64,
+    total: usize,
+    completed: AtomicUsize,
+    succeeded: AtomicUsize,
+    failed: AtomicUsize,
+    skipped: AtomicUsize,
+    external_actions: AtomicUsize,
+    injected_actions: AtomicUsize,
+    // Injections still queued or running
+    pending: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+impl JobState {
+    fn summary(&self) -> JobSummary {
+        JobSummary {
+            job_id: self.id,
+            succeeded: self.succeeded.load(Ordering::SeqCst),
+            failed: self.failed.load(Ordering::SeqCst),
+            cancelled: self.skipped.load(Ordering::SeqCst),
+            external_actions: self.external_actions.load(Ordering::SeqCst),
+            injected_actions: self.injected_actions.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// Shared view of a submitted job, used for progress display and cancellation.
+#[derive(Clone)]
+pub struct JobHandle {
+    state: Arc<JobState>,
+}
+
+impl JobHandle {
+    pub fn id(&self) -> u64 {
+        self.state.id
+    }
+
+    pub fn total(&self) -> usize {
+        self.state.total
+    }
+
+    /// Injections that have finished, failed or been skipped.
+    pub fn completed(&self) -> usize {
+        self.state.completed.load(Ordering::SeqCst)
+    }
+
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+struct WorkUnit {
+    job: Arc<JobState>,
+    injection: PlannedInjection,
+}
+
+/// Carries out one planned injection, recording the actions that took effect.
+type Executor = dyn Fn(&PlannedInjection, bool, &mut Vec<(InjectionAction, Strategy)>) -> Result<(), String> + Send + Sync;
+
+pub struct InjectionPool {
+    /// One queue per worker, picked by PID.
+    work_senders: Vec<Sender<WorkUnit>>,
+    event_sender: Sender<InjectionEvent>,
+    event_receiver: Receiver<InjectionEvent>,
+    notify: Arc<dyn Fn() + Send + Sync>,
+    prefer_external: Arc<AtomicBool>,
+    next_job_id: u64,
+}
+
+/// Worker count used by the GUI and CLI. Injections mostly wait on the
+/// target process, so a few threads are enough even on small machines.
+pub fn default_workers() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(2)
+        .clamp(2, 4)
+}
+
+impl InjectionPool {
+    /// Starts `workers` threads. `notify` runs after every event so a UI can
+    /// schedule a repaint. `prefer_external` enables non-injecting strategies.
+    pub fn new(workers: usize, prefer_external: bool, notify: impl Fn() + Send + Sync + 'static) -> Self {
+        Self::with_executor(workers, prefer_external, notify, execute)
+    }
+
+    fn with_executor(
+        workers: usize,
+        prefer_external: bool,
+        notify: impl Fn() + Send + Sync + 'static,
+        executor: impl Fn(&PlannedInjection, bool, &mut Vec<(InjectionAction, Strategy)>) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        let (event_sender, event_receiver) = crossbeam_channel::unbounded();
+        let notify: Arc<dyn Fn() + Send + Sync> = Arc::new(notify);
+        let executor: Arc<Executor> = Arc::new(executor);
+        let prefer_external = Arc::new(AtomicBool::new(prefer_external));
+
+        let work_senders = (0..workers.max(1))
+            .map(|_| {
+                let (work_sender, work_receiver) = crossbeam_channel::unbounded::<WorkUnit>();
+                let event_sender = event_sender.clone();
+                let notify = notify.clone();
+                let executor = executor.clone();
+                let prefer_external = prefer_external.clone();
+                // Workers exit once the pool (and with it the work senders) is dropped
+                std::thread::spawn(move || {
+                    while let Ok(unit) = work_receiver.recv() {
+                        let prefer_external = prefer_external.load(Ordering::SeqCst);
+                        run_unit(unit, prefer_external, executor.as_ref(), &event_sender, notify.as_ref());
+                    }
+                });
+                work_sender
+            })
+            .collect();
+
+        Self {
+            work_senders,
+            event_sender,
+            event_receiver,
+            notify,
+            prefer_external,
+            next_job_id: 1,
+        }
+    }
+
+    /// Applies to injections that have not started yet.
+    pub fn set_prefer_external(&self, prefer_external: bool) {
+        self.prefer_external.store(prefer_external, Ordering::SeqCst);
+    }
+
+    /// Plans `tasks` into one injection per process, queues them as one job
+    /// and returns immediately.
+    pub fn submit(&mut self, tasks: Vec<InjectionTask>) -> JobHandle {
+        let id = self.next_job_id;
+        self.next_job_id += 1;
+
+        let injections = planner::plan(&tasks);
+
+        let state = Arc::new(JobState {
+            id,
+            total: injections.len(),
+            completed: AtomicUsize::new(0),
+            succeeded: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+            skipped: AtomicUsize::new(0),
+            external_actions: AtomicUsize::new(0),
+            injected_actions: AtomicUsize::new(0),
+            pending: AtomicUsize::new(injections.len()),
+            cancelled: AtomicBool::new(false),
+        });
+
+        if injections.is_empty() {
+            let _ = self.event_sender.send(InjectionEvent::Finished(state.summary()));
+            (self.notify)();
+        }
+
+        for injection in injections {
+            let worker = injection.pid as usize % self.work_senders.len();
+            let _ = self.work_senders[worker].send(WorkUnit { job: state.clone(), injection });
+        }
+
+        JobHandle { state }
+    }
+
+    /// Next pending event, without blocking.
+    pub fn try_recv(&self) -> Option<InjectionEvent> {
+        self.event_receiver.try_recv().ok()
+    }
+
+    /// Waits for the next event. Used by the CLI, which has no UI loop.
+    #[allow(dead_code)]
+    pub fn recv(&self) -> Option<InjectionEvent> {
+        self.event_receiver.recv().ok()
+    }
+}
+
+fn run_unit(
+    unit: WorkUnit,
+    prefer_external: bool,
+    execute: &Executor,
+    events: &Sender<InjectionEvent>,
+    notify: &(dyn Fn() + Send + Sync),
+) {
+    let WorkUnit { job, injection } = unit;
+    let mut applied = Vec::new();
+
+    let outcome = if job.cancelled.load(Ordering::SeqCst) {
+        job.skipped.fetch_add(1, Ordering::SeqCst);
+        TaskOutcome::Cancelled
+    } else {
+        match execute(&injection, prefer_external, &mut applied) {
+            Ok(()) => {
+                job.succeeded.fetch_add(1, Ordering::SeqCst);
+                TaskOutcome::Done
+            }
+            Err(e) => {
+                job.failed.fetch_add(1, Ordering::SeqCst);
+                TaskOutcome::Failed(e)
+            }
+        }
+    };
+    for (_, strategy) in &applied {
+        match strategy {
+            Strategy::External => job.external_actions.fetch_add(1, Ordering::SeqCst),
+            Strategy::Injection => job.injected_actions.fetch_add(1, Ordering::SeqCst),
+        };
+    }
+    job.completed.fetch_add(1, Ordering::SeqCst);
+    let _ = events.send(InjectionEvent::Task(TaskResult { injection, outcome, applied }));
+    notify();
+
+    // Last injection of the job reports the summary
+    if job.pending.fetch_sub(1, Ordering::SeqCst) == 1 {
+        let _ = events.send(InjectionEvent::Finished(job.summary()));
+        notify();
+    }
+}
+
+/// Runs every action of `injection` with its chosen strategy. Actions that
+/// need the payload are combined into a single injection at the end.
+fn execute(injection: &PlannedInjection, prefer_external: bool, applied: &mut Vec<(InjectionAction, Strategy)>) -> Result<(), String> {
+    let own_process = injection.pid == std::process::id();
+    let mut to_inject = Vec::new();
+
+    for &action in &injection.actions {
+        match planner::choose_strategy(action, own_process, prefer_external) {
+            Strategy::External => match apply_external(injection, action, set_external) {
+                Ok(actions) => applied.extend(actions.into_iter().map(|action| (action, Strategy::External))),
+                Err(e) if own_process => return Err(e),
+                // e.g. UIPI refused the style change; the payload still works
+                Err(_) => to_inject.push(action),
+            },
+            Strategy::Injection => to_inject.push(action),
+        }
+    }
+
+    if !to_inject.is_empty() {
+        inject_payload(injection.pid, &to_inject)?;
+        applied.extend(to_inject.into_iter().map(|action| (action, Strategy::Injection)));
+    }
+    Ok(())
+}
+
+/// Applies `action`'s dimension window by window through `apply`. Each window
+/// that asked for a change of that kind gets its own last request, not the
+/// merged `action`. Returns the actions applied.
+fn apply_external(
+    injection: &PlannedInjection,
+    action: InjectionAction,
+    mut apply: impl FnMut(HWND, InjectionAction) -> Result<(), String>,
+) -> Result<Vec<InjectionAction>, String> {
+    let mut applied = Vec::new();
+    for (hwnd, action) in planner::window_actions(&injection.tasks, action) {
+        apply(hwnd, action)?;
+        if !applied.contains(&action) {
+            applied.push(action);
+        }
+    }
+    Ok(applied)
+}
+
+fn set_external(hwnd: HWND, action: InjectionAction) -> Result<(), String> {
+    match action {
+        InjectionAction::HideCapture => set_capture_self(hwnd, true),
+        InjectionAction::ShowCapture => set_capture_self(hwnd, false),
+        InjectionAction::HideTaskbar => set_taskbar_visibility_external(hwnd, true),
+        InjectionAction::ShowTaskbar => set_taskbar_visibility_external(hwnd, false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    fn task(pid: u32, action: InjectionAction) -> Vec<InjectionTask> {
+        vec![InjectionTask { hwnd: HWND(pid as isize * 16), pid, action }]
+    }
+
+    #[test]
+    fn injections_into_one_process_keep_their_order_across_jobs() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let seen = log.clone();
+        let mut pool = InjectionPool::with_executor(4, false, || {}, move |injection, _, applied| {
+            // The hide is slow, so a parallel show would finish first
+            if injection.actions.contains(&InjectionAction::HideCapture) {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            seen.lock().unwrap().push((injection.pid, injection.actions.clone()));
+            applied.extend(injection.actions.iter().map(|&a| (a, Strategy::Injection)));
+            Ok(())
+        });
+
+        pool.submit(task(7, InjectionAction::HideCapture));
+        pool.submit(task(7, InjectionAction::ShowCapture));
+        pool.submit(task(8, InjectionAction::HideTaskbar));
+
+        let mut finished = 0;
+        while finished < 3 {
+            if let Some(InjectionEvent::Finished(_)) = pool.recv() {
+                finished += 1;
+            }
+        }
+        let order: Vec<_> = log.lock().unwrap().iter().filter(|(pid, _)| *pid == 7).map(|(_, actions)| actions.clone()).collect();
+        assert_eq!(order, vec![vec![InjectionAction::HideCapture], vec![InjectionAction::ShowCapture]]);
+        assert_eq!(log.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn cancel_skips_injections_that_have_not_started() {
+        let (started_sender, started) = crossbeam_channel::unbounded();
+        let (release, released) = crossbeam_channel::unbounded::<()>();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counted = runs.clone();
+        // One worker, held inside the first injection until the job is cancelled
+        let mut pool = InjectionPool::with_executor(1, false, || {}, move |_, _, _| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            let _ = started_sender.send(());
+            let _ = released.recv();
+            Ok(())
+        });
+
+        let job = pool.submit([task(1, InjectionAction::HideCapture), task(2, InjectionAction::HideCapture), task(3, InjectionAction::HideCapture)].concat());
+        started.recv().unwrap();
+        job.cancel();
+        release.send(()).unwrap();
+
+        let mut outcomes = Vec::new();
+        let summary = loop {
+            match pool.recv() {
+                Some(InjectionEvent::Task(result)) => outcomes.push((result.injection.pid, matches!(result.outcome, TaskOutcome::Cancelled))),
+                Some(InjectionEvent::Finished(summary)) => break summary,
+                None => panic!("the pool stopped before the job finished"),
+            }
+        };
+        assert_eq!(outcomes, vec![(1, false), (2, true), (3, true)]);
+        assert_eq!((summary.succeeded, summary.failed, summary.cancelled), (1, 0, 2));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert!(job.is_cancelled());
+        assert_eq!(job.completed(), job.total());
+    }
+
+    #[test]
+    fn progress_reaches_the_total() {
+        let mut pool = InjectionPool::with_executor(2, false, || {}, |injection, _, applied| {
+            applied.extend(injection.actions.iter().map(|&a| (a, Strategy::Injection)));
+            Ok(())
+        });
+        let tasks: Vec<_> = (1..=5).flat_map(|pid| task(pid, InjectionAction::HideTaskbar)).collect();
+        let job = pool.submit(tasks);
+        assert_eq!(job.total(), 5);
+
+        let mut reported = 0;
+        let summary = loop {
+            match pool.recv() {
+                Some(InjectionEvent::Task(_)) => {
+                    reported += 1;
+                    // Counted before the event is sent
+                    assert!(job.completed() >= reported);
+                }
+                Some(InjectionEvent::Finished(summary)) => break summary,
+                None => panic!("the pool stopped before the job finished"),
+            }
+        };
+        assert_eq!(reported, 5);
+        assert_eq!(job.completed(), job.total());
+        assert_eq!((summary.job_id, summary.succeeded, summary.injected_actions), (job.id(), 5, 5));
+    }
+
+    #[test]
+    fn external_calls_give_each_window_its_own_action() {
+        let tasks = vec![
+            InjectionTask { hwnd: HWND(1), pid: 7, action: InjectionAction::HideTaskbar },
+            InjectionTask { hwnd: HWND(2), pid: 7, action: InjectionAction::ShowTaskbar },
+        ];
+        let injection = &planner::plan(&tasks)[0];
+        assert_eq!(injection.actions, vec![InjectionAction::ShowTaskbar]);
+
+        let mut calls = Vec::new();
+        let applied = apply_external(injection, injection.actions[0], |hwnd, action| {
+            calls.push((hwnd, action));
+            Ok(())
+        });
+        assert_eq!(calls, vec![(HWND(1), InjectionAction::HideTaskbar), (HWND(2), InjectionAction::ShowTaskbar)]);
+        assert_eq!(applied, Ok(vec![InjectionAction::HideTaskbar, InjectionAction::ShowTaskbar]));
+    }
+}
@@ -25,25 +25,55 @@
 
 #![windows_subsystem = "windows"]
 
+mod displays;
+mod download;
+mod downscale;
+mod dryrun;
+mod elevation;
+mod frame;
+mod injector;
+mod letterbox;
+mod mosaic;
+mod overlay;
+mod pacing;
+mod paths;
+mod planner;
+mod proxy;
+mod recording;
+mod schema;
+mod semver;
+#[cfg(test)]
+mod test_server;
+mod update;
+mod verify;
+
 use eframe::egui;
+use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
 use raw_window_handle::{HasWindowHandle, RawWindowHandle};
 use std::ffi::c_void; 
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::path::{Path, PathBuf};
 use std::env;
+use std::collections::HashMap;
 use chrono::Datelike; 
 
-use windows::core::{s};
+use windows::core::{s, w, PCWSTR};
 use windows::Win32::Foundation::*;
 use windows::Win32::System::Diagnostics::Debug::*;
 use windows::Win32::System::LibraryLoader::*;
 use windows::Win32::System::Memory::*;
+use windows::Win32::System::Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD, RRF_RT_REG_SZ};
+use windows::Win32::Security::Cryptography::{CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB};
 use windows::Win32::System::Threading::*;
 use windows::Win32::UI::WindowsAndMessaging::*;
 use windows::Win32::System::Diagnostics::ToolHelp::*;
 use windows::Win32::Graphics::Gdi::*;
+use windows::Win32::Storage::Xps::{PrintWindow, PRINT_WINDOW_FLAGS};
+use windows::Win32::UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI};
+use windows::Win32::UI::Shell::{DefSubclassProc, SetWindowSubclass, ShellExecuteW};
 use windows::Win32::UI::WindowsAndMessaging::KBDLLHOOKSTRUCT;
 use std::sync::atomic::{AtomicUsize, AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
 
 // WGC Imports
 use windows_capture::{
@@ -52,8 +82,9 @@ use windows_capture::{
     monitor::Monitor,
     settings::{
         ColorFormat, CursorCaptureSettings, DirtyRegionSettings, DrawBorderSettings,
-        MinimumUpdateIntervalSettings, SecondaryWindowSettings, Settings,
+        MinimumUpdateIntervalSettings, SecondaryWindowSettings, Settings, TryIntoCaptureItemWithType,
     },
+    window::Window,
 };
 
 // ===============================
@@ -63,9 +94,13 @@ use windows_capture::{
 const REPO_OWNER: &str = "aamitn";
 const REPO_NAME: &str = "mozilla-firefox";
 const APP_NAME: &str = "Mozilla Firefox";
-const APP_VERSION_DEFAULT: &str = "v1.0.0";
-const VERSION_FILE: &str = "appver.txt";
+// Baked in by build.rs
+const APP_VERSION: &str = env!("WINHIDER_VERSION");
+const BUILD_COMMIT: &str = env!("WINHIDER_COMMIT");
+const BUILD_DATE: &str = env!("WINHIDER_BUILD_DATE");
+const INSTALLER_ASSET: &str = "WinhiderInstaller.exe";
 const USER_AGENT: &str = "Mozilla-Firefox-App";
+const RESTORE_SESSION_ARG: &str = "--restore-session";
 
 
 // Windows to ignore in the list
@@ -83,15 +118,127 @@ pub const IGNORED_WINDOWS: &[&str] = &[
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 struct AppSettings {
+    /// Layout of the file; see schema.rs.
+    #[serde(default)]
+    schema_version: u32,
+    #[serde(default = "default_enable_auto_update")]
     enable_auto_update: bool,
     #[serde(default = "default_preview_quality")]
     preview_quality: u32,
+    #[serde(default = "default_prefer_external_strategies")]
+    prefer_external_strategies: bool,
+    #[serde(default = "default_clip_fps")]
+    clip_fps: u32,
+    #[serde(default = "default_clip_duration_secs")]
+    clip_duration_secs: u32,
+    #[serde(default)]
+    clip_format: recording::ClipFormat,
+    #[serde(default = "default_preview_max_fps")]
+    preview_max_fps: u32,
+    #[serde(default = "default_preview_capture_cursor")]
+    preview_capture_cursor: bool,
+    #[serde(default = "default_preview_draw_border")]
+    preview_draw_border: bool,
+    #[serde(default)]
+    preview_overlay: bool,
+    #[serde(default)]
+    update_channel: update::Channel,
+    #[serde(default = "default_update_source")]
+    update_source: update::Source,
+    /// Version that last ran, to show its release notes once after an upgrade.
+    #[serde(default)]
+    last_seen_version: Option<String>,
+    #[serde(default)]
+    proxy: proxy::ProxySettings,
+}
+
+fn default_enable_auto_update() -> bool {
+    true
 }
 
 fn default_preview_quality() -> u32 {
     2  // Default: Medium quality (scale factor 2)
 }
 
+fn default_prefer_external_strategies() -> bool {
+    true  // Default: inject only when an external call cannot do the job
+}
+
+fn default_clip_fps() -> u32 {
+    10
+}
+
+fn default_clip_duration_secs() -> u32 {
+    5
+}
+
+fn default_preview_max_fps() -> u32 {
+    30
+}
+
+fn default_preview_capture_cursor() -> bool {
+    true
+}
+
+fn default_preview_draw_border() -> bool {
+    true  // Default: let Windows draw its usual capture border
+}
+
+fn default_update_source() -> update::Source {
+    update::Source::github(REPO_OWNER, REPO_NAME)
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        AppSettings {
+            schema_version: schema::SCHEMA_VERSION,
+            enable_auto_update: default_enable_auto_update(),
+            preview_quality: default_preview_quality(),
+            prefer_external_strategies: default_prefer_external_strategies(),
+            clip_fps: default_clip_fps(),
+            clip_duration_secs: default_clip_duration_secs(),
+            clip_format: recording::ClipFormat::default(),
+            preview_max_fps: default_preview_max_fps(),
+            preview_capture_cursor: default_preview_capture_cursor(),
+            preview_draw_border: default_preview_draw_border(),
+            preview_overlay: false,
+            update_channel: update::Channel::default(),
+            update_source: default_update_source(),
+            last_seen_version: None,
+            proxy: proxy::ProxySettings::default(),
+        }
+    }
+}
+
+/// Numeric settings and the values they may take.
+const SETTINGS_LIMITS: &[schema::Limit] = &[
+    schema::Limit { field: "preview_quality", min: 1, max: 3 },
+    schema::Limit { field: "clip_fps", min: 1, max: recording::MAX_FPS as u64 },
+    schema::Limit { field: "clip_duration_secs", min: 1, max: recording::MAX_DURATION_SECS as u64 },
+    schema::Limit { field: "preview_max_fps", min: pacing::MIN_FPS as u64, max: pacing::MAX_FPS as u64 },
+];
+
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+struct SessionState {
+    windows: Vec<WindowSessionState>,
+    selected_hwnds: Vec<isize>,
+    selected_monitor_idx: usize,
+    #[serde(default)]
+    preview_hwnd: Option<isize>,
+    #[serde(default)]
+    preview_mosaic: bool,
+    show_preview: bool,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct WindowSessionState {
+    hwnd: isize,
+    pid: u32,
+    is_taskbar_hidden: bool,
+    is_capture_hidden: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum InjectionAction {
     HideCapture,
     ShowCapture,
@@ -99,12 +246,32 @@ enum InjectionAction {
     ShowTaskbar,
 }
 
+/// Tasks held back by dry-run mode until the user applies them.
+struct DryRunPreview {
+    label: String,
+    tasks: Vec<planner::InjectionTask>,
+    diff: Vec<dryrun::WindowDiff>,
+    /// New windows auto-hide would hide. Discarding the preview takes them
+    /// off the window list, so the next refresh offers them again.
+    new_windows: Vec<HWND>,
+}
+
+/// The update dialog's installer download.
+#[derive(Clone, PartialEq)]
+enum InstallerDownload {
+    Idle,
+    Running(download::Progress),
+    /// Downloaded and matched the published SHA-256.
+    Verified(download::Installer),
+    Failed(String),
+}
+
 #[derive(Clone, PartialEq)]
 enum UpdateStatus {
     Idle,
     Checking,
     UpToDate,
-    UpdateAvailable(String), 
+    UpdateAvailable(update::Update),
     Error(String),
 }
 
@@ -116,25 +283,89 @@ pub struct AppWindow {
     pub is_taskbar_hidden: bool,
     pub is_capture_hidden: bool, 
     pub icon_texture: Option<egui::TextureHandle>,
+    pub token: Option<elevation::TokenInfo>,
+    pub requires_admin: bool,
 }
 
 // ===============================
 // WGC HANDLER
 // ===============================
 
+/// Passed to the capture thread when a session starts; a quality, frame
+/// rate, cursor or border change restarts the session with new flags.
+#[derive(Clone)]
+struct CaptureFlags {
+    sender: crossbeam_channel::Sender<egui::ColorImage>,
+    preview_quality: u32,
+    /// Woken when a frame is sent, so the UI repaints on arrival.
+    repaint: Option<egui::Context>,
+    /// Current frame budget; the UI lowers it in the background.
+    pace: Arc<pacing::SharedInterval>,
+    max_fps: u32,
+    capture_cursor: bool,
+    draw_border: bool,
+}
+
+impl CaptureFlags {
+    /// Flags for a session nobody watches live: every frame at the highest
+    /// quality, with the saved cursor and border settings.
+    fn headless(sender: crossbeam_channel::Sender<egui::ColorImage>) -> Self {
+        let settings = load_settings();
+        CaptureFlags {
+            sender,
+            preview_quality: 3,
+            repaint: None,
+            pace: Arc::default(),
+            max_fps: pacing::MAX_FPS,
+            capture_cursor: settings.preview_capture_cursor,
+            draw_border: settings.preview_draw_border,
+        }
+    }
+}
+
+type PreviewControl = CaptureControl<WgcHandler, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Starts a capture session for a monitor or a window.
+fn start_capture<T: TryIntoCaptureItemWithType + Clone + Send + 'static>(item: T, flags: CaptureFlags) -> Result<PreviewControl, String> {
+    let cursor = if flags.capture_cursor { CursorCaptureSettings::Default } else { CursorCaptureSettings::WithoutCursor };
+    let border = if flags.draw_border { DrawBorderSettings::Default } else { DrawBorderSettings::WithoutBorder };
+    let interval = pacing::session_interval(flags.max_fps, recording::MAX_FPS);
+
+    let settings = |update_interval, flags| Settings::new(
+        item.clone(),
+        cursor,
+        border,
+        SecondaryWindowSettings::Default,
+        update_interval,
+        DirtyRegionSettings::Default,
+        ColorFormat::Rgba8,
+        flags,
+    );
+    // Older Windows builds cannot limit the update rate; the pacer still does
+    WgcHandler::start_free_threaded(settings(MinimumUpdateIntervalSettings::Custom(interval), flags.clone()))
+        .or_else(|_| WgcHandler::start_free_threaded(settings(MinimumUpdateIntervalSettings::Default, flags)))
+        .map_err(|e| format!("Failed to start Graphics Capture: {}", e))
+}
+
 struct WgcHandler {
     sender: crossbeam_channel::Sender<egui::ColorImage>,
     preview_quality: u32,
+    repaint: Option<egui::Context>,
+    pace: Arc<pacing::SharedInterval>,
+    pacer: pacing::Pacer,
 }
 
 impl GraphicsCaptureApiHandler for WgcHandler {
-    type Flags = crossbeam_channel::Sender<egui::ColorImage>;
+    type Flags = CaptureFlags;
     type Error = Box<dyn std::error::Error + Send + Sync>;
 
     fn new(ctx: Context<Self::Flags>) -> Result<Self, Self::Error> {
         Ok(Self {
-            sender: ctx.flags,
-            preview_quality: 2,  // Default quality
+            sender: ctx.flags.sender,
+            preview_quality: ctx.flags.preview_quality,
+            repaint: ctx.flags.repaint,
+            pace: ctx.flags.pace,
+            pacer: pacing::Pacer::default(),
         })
     }
 
@@ -146,38 +377,39 @@ impl GraphicsCaptureApiHandler for WgcHandler {
         if self.sender.is_full() {
             return Ok(());
         }
+        // Over budget: drop before touching the buffer
+        if !self.pacer.accept(Instant::now(), self.pace.get()) {
+            return Ok(());
+        }
+
+        let format = match frame.color_format() {
+            ColorFormat::Rgba8 => frame::PixelFormat::Rgba8,
+            ColorFormat::Bgra8 => frame::PixelFormat::Bgra8,
+            // The session never asks for float frames
+            _ => return Ok(()),
+        };
 
-        let width = frame.width();
-        let height = frame.height();
-        
         if let Ok(mut buffer) = frame.buffer() {
-            if let Ok(raw_slice) = buffer.as_nopadding_buffer() {
-                // Nearest Neighbor Downscale (Fast)
-                let scale_factor = 3; 
-                let new_width = width / scale_factor;
-                let new_height = height / scale_factor;
-                
-                let mut fast_buffer = Vec::with_capacity((new_width * new_height * 4) as usize);
-                let stride = (width * 4) as usize; 
-                
-                for y in 0..new_height {
-                    let src_y_offset = (y * scale_factor) as usize * stride;
-                    for x in 0..new_width {
-                        let src_x_offset = (x * scale_factor) as usize * 4;
-                        let i = src_y_offset + src_x_offset;
-                        
-                        if i + 4 <= raw_slice.len() {
-                            fast_buffer.extend_from_slice(&raw_slice[i..i+4]);
-                        }
-                    }
-                }
+            let layout = frame::FrameLayout {
+                width: buffer.width(),
+                height: buffer.height(),
+                row_pitch: buffer.row_pitch() as usize,
+                format,
+            };
+            // Quality picks the target resolution and filter
+            let (max_w, max_h, filter) = downscale::quality_params(self.preview_quality);
 
+            // A malformed frame is dropped; the next one replaces it anyway
+            if let Ok(image) = frame::prepare_preview(buffer.as_raw_buffer(), &layout, max_w, max_h, filter) {
                 let egui_img = egui::ColorImage::from_rgba_unmultiplied(
-                    [new_width as usize, new_height as usize],
-                    &fast_buffer,
+                    [image.width as usize, image.height as usize],
+                    &image.pixels,
                 );
 
-                let _ = self.sender.try_send(egui_img);
+                let sent = self.sender.try_send(egui_img).is_ok();
+                if let Some(ctx) = self.repaint.as_ref().filter(|_| sent) {
+                    ctx.request_repaint();
+                }
             }
         }
 
@@ -185,6 +417,16 @@ impl GraphicsCaptureApiHandler for WgcHandler {
     }
 }
 
+/// One monitor's capture session in the mosaic preview.
+struct MosaicTile {
+    control: PreviewControl,
+    receiver: crossbeam_channel::Receiver<egui::ColorImage>,
+    texture: Option<egui::TextureHandle>,
+    rect: verify::ScreenRect,
+    /// Monitor number as shown in the source selector.
+    number: usize,
+}
+
 // ===============================
 // MAIN APP STATE
 // ===============================
@@ -202,15 +444,56 @@ struct WinHiderApp {
     
     // Preview Fields
     monitors: Vec<Monitor>,
+    /// Descriptions of `monitors`, index for index.
+    monitor_info: Vec<displays::DisplayInfo>,
     selected_monitor_idx: usize,
+    /// Last display change notification, acted on once changes settle.
+    display_change_at: Option<Instant>,
+    /// Window shown in the preview instead of the selected monitor.
+    preview_window: Option<HWND>,
+    /// Show every monitor at once instead of the selected one.
+    preview_mosaic: bool,
+    mosaic_tiles: Vec<MosaicTile>,
     show_preview: bool,
     preview_texture: Option<egui::TextureHandle>,
     preview_quality: u32,
+    preview_max_fps: u32,
+    preview_capture_cursor: bool,
+    preview_draw_border: bool,
+    /// Frame budget shared with the capture thread.
+    preview_pace: Arc<pacing::SharedInterval>,
+    egui_ctx: egui::Context,
+    /// Outline hidden windows on a monitor preview.
+    show_overlay: bool,
+    /// List row to bring into view after a click on its outline.
+    scroll_to_window: Option<HWND>,
+    /// CPU copy of the frame behind `preview_texture`, for snapshots.
+    last_frame: Option<Arc<egui::ColorImage>>,
+
+    // Snapshots & Clips
+    recorder: Option<recording::ClipRecorder>,
+    clip_fps: u32,
+    clip_duration_secs: u32,
+    clip_format: recording::ClipFormat,
+    clip_saving: bool,
+    clip_sender: crossbeam_channel::Sender<Result<PathBuf, String>>,
+    clip_receiver: crossbeam_channel::Receiver<Result<PathBuf, String>>,
     
     // UI State
     show_about_dialog: bool,
     show_update_dialog: bool,
+    /// Keep the dialog open for the result, even when there is no update.
+    keep_update_dialog: bool,
     update_status: UpdateStatus,
+    installer_download: InstallerDownload,
+    /// Release notes shown once after an upgrade: the new version, and the
+    /// releases since the previous one.
+    whats_new: Option<(String, Result<Vec<update::Release>, String>)>,
+    /// The newest version whose release notes were shown.
+    last_seen_version: Option<String>,
+    /// What was reset while loading a damaged settings file.
+    settings_notice: Option<String>,
+    markdown_cache: CommonMarkCache,
     latest_version: Option<String>,
     auto_hide_list: Vec<String>,
     show_auto_hide_editor: bool,
@@ -219,15 +502,36 @@ struct WinHiderApp {
 
     // Settings
     enable_auto_update: bool,
+    update_channel: update::Channel,
+    update_source: update::Source,
+    proxy: proxy::ProxySettings,
+    prefer_external_strategies: bool,
+    current_token: elevation::TokenInfo,
     applied_self_hide: bool,
 
     // Communication Channels
-    capture_control: Option<CaptureControl<WgcHandler, Box<dyn std::error::Error + Send + Sync>>>,
+    capture_control: Option<PreviewControl>,
     frame_receiver: crossbeam_channel::Receiver<egui::ColorImage>,
     frame_sender: crossbeam_channel::Sender<egui::ColorImage>, 
     
-    update_sender: crossbeam_channel::Sender<UpdateStatus>,
-    update_receiver: crossbeam_channel::Receiver<UpdateStatus>,
+    update_sender: crossbeam_channel::Sender<(update::Channel, UpdateStatus)>,
+    update_receiver: crossbeam_channel::Receiver<(update::Channel, UpdateStatus)>,
+    download_sender: crossbeam_channel::Sender<InstallerDownload>,
+    download_receiver: crossbeam_channel::Receiver<InstallerDownload>,
+    whats_new_sender: crossbeam_channel::Sender<(String, Result<Vec<update::Release>, String>)>,
+    whats_new_receiver: crossbeam_channel::Receiver<(String, Result<Vec<update::Release>, String>)>,
+
+    // Background Injection
+    injector: injector::InjectionPool,
+    active_jobs: Vec<(injector::JobHandle, String)>,
+    dry_run: bool,
+    pending_preview: Option<DryRunPreview>,
+
+    // Capture Verification (keyed by HWND)
+    verifications: HashMap<isize, verify::Verification>,
+    verifying: Vec<HWND>,
+    verify_sender: crossbeam_channel::Sender<(HWND, Result<verify::Verification, String>)>,
+    verify_receiver: crossbeam_channel::Receiver<(HWND, Result<verify::Verification, String>)>,
 }
 
 impl WinHiderApp {
@@ -235,12 +539,9 @@ impl WinHiderApp {
     fn new(cc: &eframe::CreationContext<'_>) -> Self {
         clean_temp_files();
         
-        let app_version = std::fs::read_to_string(VERSION_FILE)
-            .unwrap_or_else(|_| APP_VERSION_DEFAULT.to_string())
-            .trim()
-            .to_string();
+        let app_version = APP_VERSION.to_string();
 
-        let settings = load_settings();
+        let (settings, settings_notice) = load_settings_with_notice();
 
         // --- LOAD APP ICON FOR UI ---
         // This embeds the icon into the binary so it works even if the .ico file is deleted
@@ -257,7 +558,18 @@ impl WinHiderApp {
 
         let (tx, rx) = crossbeam_channel::bounded(1);
         let (up_tx, up_rx) = crossbeam_channel::unbounded(); 
-        let monitors = Monitor::enumerate().unwrap_or_default();
+        let (verify_tx, verify_rx) = crossbeam_channel::unbounded();
+        let (clip_tx, clip_rx) = crossbeam_channel::unbounded();
+        let (download_tx, download_rx) = crossbeam_channel::unbounded();
+        let (whats_new_tx, whats_new_rx) = crossbeam_channel::unbounded();
+        let (monitors, monitor_info) = enumerate_monitors();
+
+        let repaint_ctx = cc.egui_ctx.clone();
+        let injector = injector::InjectionPool::new(
+            injector::default_workers(),
+            settings.prefer_external_strategies,
+            move || repaint_ctx.request_repaint(),
+        );
 
         let mut app = Self {
             app_version,
@@ -269,19 +581,50 @@ impl WinHiderApp {
             last_refresh: SystemTime::UNIX_EPOCH,
             
             monitors,
+            monitor_info,
             selected_monitor_idx: 0,
+            display_change_at: None,
+            preview_window: None,
+            preview_mosaic: false,
+            mosaic_tiles: Vec::new(),
             show_preview: true,
             preview_texture: None,
             preview_quality: settings.preview_quality,
+            preview_max_fps: settings.preview_max_fps,
+            preview_capture_cursor: settings.preview_capture_cursor,
+            preview_draw_border: settings.preview_draw_border,
+            preview_pace: Arc::new(pacing::SharedInterval::default()),
+            egui_ctx: cc.egui_ctx.clone(),
+            show_overlay: settings.preview_overlay,
+            scroll_to_window: None,
+            last_frame: None,
+            recorder: None,
+            clip_fps: settings.clip_fps,
+            clip_duration_secs: settings.clip_duration_secs,
+            clip_format: settings.clip_format,
+            clip_saving: false,
+            clip_sender: clip_tx,
+            clip_receiver: clip_rx,
             show_about_dialog: false,
             show_update_dialog: false,
+            keep_update_dialog: false,
             update_status: UpdateStatus::Idle,
+            installer_download: InstallerDownload::Idle,
+            whats_new: None,
+            last_seen_version: settings.last_seen_version.clone(),
+            settings_notice,
+            markdown_cache: CommonMarkCache::default(),
             latest_version: None,
             auto_hide_list: load_auto_hide_list(),
             show_auto_hide_editor: false,
             new_app_input: String::new(),
             selected_window_idx: Vec::new(),
             enable_auto_update: settings.enable_auto_update,
+            update_channel: settings.update_channel,
+            update_source: settings.update_source,
+            proxy: settings.proxy,
+            prefer_external_strategies: settings.prefer_external_strategies,
+            current_token: elevation::current_token(),
             applied_self_hide: false,
             
             capture_control: None,
@@ -290,12 +633,43 @@ impl WinHiderApp {
             
             update_sender: up_tx,
             update_receiver: up_rx,
+            download_sender: download_tx,
+            download_receiver: download_rx,
+            whats_new_sender: whats_new_tx,
+            whats_new_receiver: whats_new_rx,
+
+            injector,
+            active_jobs: Vec::new(),
+            dry_run: false,
+            pending_preview: None,
+
+            verifications: HashMap::new(),
+            verifying: Vec::new(),
+            verify_sender: verify_tx,
+            verify_receiver: verify_rx,
         };
 
+        if env::args().any(|a| a == RESTORE_SESSION_ARG) {
+            if let Some(session) = take_session() {
+                app.restore_session(&cc.egui_ctx, session);
+            }
+        }
+
         app.start_capture_session();
         
+        // Release notes once after an upgrade; a first run has nothing to compare with.
+        // After an upgrade the version counts as seen once its notes have arrived.
+        let upgraded_from = settings.last_seen_version.clone()
+            .filter(|seen| semver::is_newer(seen, &app.app_version).unwrap_or(false));
+        if upgraded_from.is_none() && app.last_seen_version.as_deref() != Some(app.app_version.as_str()) {
+            app.last_seen_version = Some(app.app_version.clone());
+            app.save_current_settings();
+        }
+
         if app.enable_auto_update {
-            app.check_for_updates();
+            app.check_for_updates(false, upgraded_from);
+        } else if let Some(previous) = upgraded_from {
+            app.fetch_whats_new(previous);
         }
         
         app
@@ -305,67 +679,570 @@ impl WinHiderApp {
         if let Some(ctrl) = self.capture_control.take() {
             let _ = ctrl.stop();
         }
+        for tile in self.mosaic_tiles.drain(..) {
+            let _ = tile.control.stop();
+        }
+        // Frames from the old session may have a different size or orientation
+        while self.frame_receiver.try_recv().is_ok() {}
+        self.preview_texture = None;
+        self.last_frame = None;
+
+        if !self.show_preview {
+            // Keep what was recorded so far
+            if self.recorder.is_some() {
+                self.finish_recording();
+            }
+            return;
+        }
+
+        // The window may have gone away while the preview was off
+        if self.preview_window.is_some_and(|hwnd| unsafe { !IsWindow(hwnd).as_bool() }) {
+            self.preview_window = None;
+        }
+
+        let flags = CaptureFlags {
+            sender: self.frame_sender.clone(),
+            preview_quality: self.preview_quality,
+            repaint: Some(self.egui_ctx.clone()),
+            pace: self.preview_pace.clone(),
+            max_fps: self.preview_max_fps,
+            capture_cursor: self.preview_capture_cursor,
+            draw_border: self.preview_draw_border,
+        };
+
+        if self.preview_mosaic && self.preview_window.is_none() {
+            // The clip recorder follows a single frame stream
+            if self.recorder.is_some() {
+                self.finish_recording();
+            }
+            self.start_mosaic(flags);
+            return;
+        }
+
+        let started = match self.preview_window {
+            Some(hwnd) => start_capture(Window::from_raw_hwnd(hwnd.0 as *mut c_void), flags),
+            None => {
+                if self.monitors.is_empty() {
+                    return;
+                }
+                if self.selected_monitor_idx >= self.monitors.len() {
+                    self.selected_monitor_idx = 0;
+                }
+                start_capture(self.monitors[self.selected_monitor_idx], flags)
+            }
+        };
+
+        match started {
+            Ok(ctrl) => self.capture_control = Some(ctrl),
+            Err(e) => self.status_msg = e,
+        }
+    }
 
-        if !self.show_preview || self.monitors.is_empty() {
+    /// Re-enumerates monitors after a display change. The preview stays on the
+    /// same physical monitor while it is attached and falls back to the
+    /// primary one otherwise.
+    fn refresh_monitors(&mut self) {
+        let (monitors, monitor_info) = enumerate_monitors();
+        self.monitors = monitors;
+        if monitor_info == self.monitor_info {
             return;
         }
 
-        if self.selected_monitor_idx >= self.monitors.len() {
-            self.selected_monitor_idx = 0;
+        let previous = self.monitor_info.get(self.selected_monitor_idx).cloned();
+        let same = previous.as_ref().and_then(|p| displays::find_same(p, &monitor_info));
+        self.monitor_info = monitor_info;
+        self.selected_monitor_idx = same
+            .or_else(|| self.monitor_info.iter().position(|d| d.primary))
+            .unwrap_or(0);
+
+        if self.monitors.len() < 2 {
+            self.preview_mosaic = false;
         }
+        if self.preview_window.is_none() {
+            if let Some(previous) = previous.filter(|_| same.is_none() && !self.preview_mosaic) {
+                self.status_msg = format!("{} was disconnected; previewing {}.", previous.name(), self.preview_source_label());
+            }
+            self.start_capture_session();
+        }
+    }
 
-        let monitor = self.monitors[self.selected_monitor_idx];
-        let sender = self.frame_sender.clone();
+    /// One capture session per monitor, each with its own frame channel. The
+    /// sessions share the frame budget, which is split between them.
+    fn start_mosaic(&mut self, flags: CaptureFlags) {
+        for (i, &monitor) in self.monitors.iter().enumerate() {
+            let Some(rect) = monitor_rect(HMONITOR(monitor.as_raw_hmonitor() as isize)) else {
+                continue;
+            };
+            let (sender, receiver) = crossbeam_channel::bounded(1);
+            match start_capture(monitor, CaptureFlags { sender, ..flags.clone() }) {
+                Ok(control) => self.mosaic_tiles.push(MosaicTile { control, receiver, texture: None, rect, number: i + 1 }),
+                Err(e) => self.status_msg = format!("Monitor {}: {}", i + 1, e),
+            }
+        }
+    }
 
-        let settings = Settings::new(
-            monitor,
-            CursorCaptureSettings::Default,
-            DrawBorderSettings::Default,
-            SecondaryWindowSettings::Default,
-            MinimumUpdateIntervalSettings::Default,
-            DirtyRegionSettings::Default,
-            ColorFormat::Rgba8,
-            sender
-        );
+    fn save_snapshot(&mut self) {
+        let Some(image) = &self.last_frame else {
+            return;
+        };
+        let path = get_captures_dir().join(format!("snapshot_{}.png", chrono::Local::now().format("%Y%m%d_%H%M%S")));
+        self.status_msg = match recording::save_snapshot(&path, &rgba_frame(image)) {
+            Ok(()) => format!("Snapshot saved to {}", path.display()),
+            Err(e) => e,
+        };
+    }
+
+    fn start_recording(&mut self) {
+        let settings = recording::ClipSettings::new(self.clip_fps, self.clip_duration_secs);
+        let mut recorder = recording::ClipRecorder::new(settings);
+        // Start from the frame on screen; the stream only sends changes
+        if let Some(image) = &self.last_frame {
+            recorder.push(Arc::new(rgba_frame(image)));
+        }
+        self.recorder = Some(recorder);
+        self.status_msg = format!("Recording {}s {} clip...", settings.duration_secs, self.clip_format.label());
+    }
+
+    /// Encodes and saves the recorded frames on a background thread.
+    fn finish_recording(&mut self) {
+        let Some(recorder) = self.recorder.take() else {
+            return;
+        };
+        let fps = recorder.settings().fps;
+        let frames = recorder.into_frames();
+        if frames.is_empty() {
+            self.status_msg = "Recording stopped before any frame arrived.".to_string();
+            return;
+        }
+
+        let format = self.clip_format;
+        let path = get_captures_dir().join(format!("clip_{}.{}", chrono::Local::now().format("%Y%m%d_%H%M%S"), format.extension()));
+        let sender = self.clip_sender.clone();
+        self.clip_saving = true;
+        self.status_msg = format!("Encoding {} frames...", frames.len());
+        std::thread::spawn(move || {
+            let _ = sender.send(recording::save_clip(&path, format, &frames, fps).map(|_| path));
+        });
+    }
+
+    fn process_clip_results(&mut self) {
+        while let Ok(result) = self.clip_receiver.try_recv() {
+            self.clip_saving = false;
+            self.status_msg = match result {
+                Ok(path) => format!("Clip saved to {}", path.display()),
+                Err(e) => e,
+            };
+        }
+    }
+
+    /// Stops a session that ended on its own, e.g. because the previewed
+    /// window was closed, and falls back to the monitor.
+    fn check_capture_session(&mut self) {
+        // A monitor that went away drops out of the mosaic
+        self.mosaic_tiles.retain(|tile| !tile.control.is_finished());
+
+        if !self.capture_control.as_ref().is_some_and(|ctrl| ctrl.is_finished()) {
+            return;
+        }
+        if let Some(ctrl) = self.capture_control.take() {
+            let _ = ctrl.stop();
+        }
+        if self.preview_window.take().is_some() {
+            self.status_msg = "Previewed window closed; showing the monitor again.".to_string();
+            self.start_capture_session();
+        }
+    }
+
+    /// Size of the preview source: the window's outer size, or the current
+    /// mode of the selected monitor in its present orientation.
+    fn preview_source_size(&self) -> (u32, u32) {
+        if let Some(hwnd) = self.preview_window {
+            let mut rect = RECT::default();
+            if unsafe { GetWindowRect(hwnd, &mut rect) }.is_ok() && rect.right > rect.left && rect.bottom > rect.top {
+                return ((rect.right - rect.left) as u32, (rect.bottom - rect.top) as u32);
+            }
+        }
+        self.monitors.get(self.selected_monitor_idx)
+            .and_then(|m| Some((m.width().ok()?, m.height().ok()?)))
+            .unwrap_or((16, 9))
+    }
 
-        if let Ok(ctrl) = WgcHandler::start_free_threaded(settings) {
-            self.capture_control = Some(ctrl);
+    /// Outlines of the capture-hidden windows inside `area`, a monitor or the
+    /// whole mosaic drawn at `image`, in pane coordinates, topmost window first.
+    fn hidden_window_outlines(&self, area: verify::ScreenRect, image: letterbox::FitRect) -> Vec<overlay::Outline> {
+        self.windows.iter()
+            .filter(|w| w.is_capture_hidden && unsafe { !IsIconic(w.hwnd).as_bool() })
+            .filter_map(|w| {
+                let mut rect = RECT::default();
+                unsafe { GetWindowRect(w.hwnd, &mut rect) }.ok()?;
+                let window = verify::ScreenRect { left: rect.left, top: rect.top, right: rect.right, bottom: rect.bottom };
+                Some(overlay::Outline {
+                    hwnd: w.hwnd.0,
+                    label: truncate_middle(&w.title, 30),
+                    rect: overlay::map_rect(window, area, image)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Selects a window from its outline, with the list's click rules:
+    /// Ctrl toggles it, a plain click makes it the only selection.
+    fn select_from_preview(&mut self, hwnd: HWND, toggle: bool) {
+        if toggle {
+            if self.selected_window_idx.contains(&hwnd) {
+                self.selected_window_idx.retain(|&h| h != hwnd);
+            } else {
+                self.selected_window_idx.push(hwnd);
+            }
         } else {
-            self.status_msg = "Failed to start Graphics Capture".to_string();
+            self.selected_window_idx = vec![hwnd];
+        }
+        self.scroll_to_window = Some(hwnd);
+        self.status_msg = format!("Selected: {} windows", self.selected_window_idx.len());
+    }
+
+    fn preview_source_label(&self) -> String {
+        match self.preview_window {
+            Some(hwnd) => {
+                let title = self.windows.iter().find(|w| w.hwnd == hwnd).map(|w| w.title.as_str()).unwrap_or("Window");
+                format!("Window: {}", truncate_middle(title, 30))
+            }
+            None if self.preview_mosaic => "All Monitors".to_string(),
+            None => match self.monitor_info.get(self.selected_monitor_idx) {
+                Some(info) => info.short_label(self.selected_monitor_idx + 1),
+                None => format!("Monitor {}", self.selected_monitor_idx + 1),
+            },
+        }
+    }
+
+    fn session_state(&self) -> SessionState {
+        SessionState {
+            windows: self.windows.iter()
+                .filter(|w| w.is_taskbar_hidden || w.is_capture_hidden)
+                .map(|w| WindowSessionState {
+                    hwnd: w.hwnd.0,
+                    pid: w.pid,
+                    is_taskbar_hidden: w.is_taskbar_hidden,
+                    is_capture_hidden: w.is_capture_hidden,
+                })
+                .collect(),
+            selected_hwnds: self.selected_window_idx.iter().map(|h| h.0).collect(),
+            selected_monitor_idx: self.selected_monitor_idx,
+            preview_hwnd: self.preview_window.map(|h| h.0),
+            preview_mosaic: self.preview_mosaic,
+            show_preview: self.show_preview,
+        }
+    }
+
+    fn restore_session(&mut self, ctx: &egui::Context, session: SessionState) {
+        // Window handles survive our restart, so the saved flags still describe live windows
+        self.windows = enumerate_windows(ctx);
+        for w in &mut self.windows {
+            if let Some(saved) = session.windows.iter().find(|s| s.hwnd == w.hwnd.0 && s.pid == w.pid) {
+                w.is_taskbar_hidden = saved.is_taskbar_hidden;
+                w.is_capture_hidden = saved.is_capture_hidden;
+            }
+        }
+        self.selected_window_idx = session.selected_hwnds.into_iter()
+            .map(HWND)
+            .filter(|h| self.windows.iter().any(|w| w.hwnd == *h))
+            .collect();
+        self.selected_monitor_idx = session.selected_monitor_idx;
+        self.preview_window = session.preview_hwnd.map(HWND).filter(|h| unsafe { IsWindow(*h).as_bool() });
+        self.preview_mosaic = session.preview_mosaic;
+        self.show_preview = session.show_preview;
+        self.last_refresh = SystemTime::now();
+        self.status_msg = "Session restored with administrator rights.".to_string();
+    }
+
+    fn relaunch_elevated(&mut self, ctx: &egui::Context) {
+        if let Err(e) = save_session(&self.session_state()) {
+            self.status_msg = format!("Failed to save session: {}", e);
+            return;
+        }
+        match elevation::relaunch_elevated(RESTORE_SESSION_ARG) {
+            Ok(_) => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+            Err(e) => {
+                let _ = take_session();
+                self.status_msg = format!("Failed to relaunch: {}", e);
+            }
+        }
+    }
+
+    fn save_current_settings(&self) {
+        let settings = AppSettings {
+            schema_version: schema::SCHEMA_VERSION,
+            enable_auto_update: self.enable_auto_update,
+            preview_quality: self.preview_quality,
+            prefer_external_strategies: self.prefer_external_strategies,
+            clip_fps: self.clip_fps,
+            clip_duration_secs: self.clip_duration_secs,
+            clip_format: self.clip_format,
+            preview_max_fps: self.preview_max_fps,
+            preview_capture_cursor: self.preview_capture_cursor,
+            preview_draw_border: self.preview_draw_border,
+            preview_overlay: self.show_overlay,
+            update_channel: self.update_channel,
+            update_source: self.update_source.clone(),
+            last_seen_version: self.last_seen_version.clone(),
+            proxy: self.proxy.clone(),
+        };
+        let _ = save_settings(&settings);
+    }
+
+    /// Hands `tasks` to the injection pool. `label` becomes the status message
+    /// once every task of the job has succeeded. In dry-run mode the tasks are
+    /// held in the preview dialog instead.
+    fn queue_injections(&mut self, tasks: Vec<planner::InjectionTask>, label: String) {
+        if tasks.is_empty() {
+            return;
+        }
+        if self.dry_run {
+            // Callers flip flags up front; nothing changes until Apply
+            self.revert_task_flags(&tasks, &[]);
+            self.open_preview(tasks, label);
+            return;
+        }
+        let job = self.injector.submit(tasks);
+        self.status_msg = format!("Working on {} process(es)...", job.total());
+        self.active_jobs.push((job, label));
+    }
+
+    /// Shows what `tasks` would change, merging into an open preview.
+    fn open_preview(&mut self, tasks: Vec<planner::InjectionTask>, label: String) {
+        let (mut all_tasks, label, new_windows) = match self.pending_preview.take() {
+            Some(preview) => (preview.tasks, format!("{} + {}", preview.label, label), preview.new_windows),
+            None => (Vec::new(), label, Vec::new()),
+        };
+        all_tasks.extend(tasks);
+
+        let states: Vec<dryrun::WindowState> = self.windows.iter()
+            .map(|w| {
+                let (capture_hidden, taskbar_hidden) = query_window_state(w.hwnd);
+                dryrun::WindowState { hwnd: w.hwnd, pid: w.pid, title: w.title.clone(), capture_hidden, taskbar_hidden }
+            })
+            .collect();
+        let diff = dryrun::diff(&states, &all_tasks, self.prefer_external_strategies, std::process::id());
+
+        self.status_msg = format!("Dry run: {} window(s) would change", diff.len());
+        self.pending_preview = Some(DryRunPreview { label, tasks: all_tasks, diff, new_windows });
+    }
+
+    /// Sets the UI flags to the state `tasks` lead to.
+    fn apply_task_flags(&mut self, tasks: &[planner::InjectionTask]) {
+        for task in tasks {
+            if let Some(w) = self.windows.iter_mut().find(|w| w.hwnd == task.hwnd) {
+                match task.action {
+                    InjectionAction::HideCapture => w.is_capture_hidden = true,
+                    InjectionAction::ShowCapture => w.is_capture_hidden = false,
+                    InjectionAction::HideTaskbar => w.is_taskbar_hidden = true,
+                    InjectionAction::ShowTaskbar => w.is_taskbar_hidden = false,
+                }
+            }
+        }
+    }
+
+    /// Undoes optimistic flag changes for tasks whose action is not in
+    /// `applied`, in reverse so repeated toggles land on the original state.
+    fn revert_task_flags(&mut self, tasks: &[planner::InjectionTask], applied: &[(InjectionAction, planner::Strategy)]) {
+        for task in tasks.iter().rev() {
+            if applied.iter().any(|(action, _)| *action == task.action) {
+                continue;
+            }
+            if let Some(w) = self.windows.iter_mut().find(|w| w.hwnd == task.hwnd) {
+                match task.action {
+                    InjectionAction::HideCapture => w.is_capture_hidden = false,
+                    InjectionAction::ShowCapture => w.is_capture_hidden = true,
+                    InjectionAction::HideTaskbar => w.is_taskbar_hidden = false,
+                    InjectionAction::ShowTaskbar => w.is_taskbar_hidden = true,
+                }
+            }
+        }
+    }
+
+    /// Applies injection results delivered by the worker pool.
+    fn process_injection_events(&mut self) {
+        let mut to_verify = Vec::new();
+        while let Some(event) = self.injector.try_recv() {
+            match event {
+                injector::InjectionEvent::Task(result) => {
+                    if let injector::TaskOutcome::Failed(e) = &result.outcome {
+                        self.status_msg = format!("Error: {}", e);
+                    }
+                    // Flags were flipped optimistically when the tasks were queued
+                    self.revert_task_flags(&result.injection.tasks, &result.applied);
+
+                    // Old results no longer apply; fresh hides get checked
+                    for task in &result.injection.tasks {
+                        if matches!(task.action, InjectionAction::HideCapture | InjectionAction::ShowCapture) {
+                            self.verifications.remove(&task.hwnd.0);
+                        }
+                    }
+                    if result.applied.iter().any(|(action, _)| *action == InjectionAction::HideCapture) {
+                        to_verify.extend(result.injection.tasks.iter()
+                            .filter(|t| t.action == InjectionAction::HideCapture)
+                            .map(|t| t.hwnd));
+                    }
+                }
+                injector::InjectionEvent::Finished(summary) => {
+                    let Some(pos) = self.active_jobs.iter().position(|(job, _)| job.id() == summary.job_id) else {
+                        continue;
+                    };
+                    let (_, label) = self.active_jobs.remove(pos);
+
+                    // Report how the actions were carried out
+                    let mut via = Vec::new();
+                    if summary.external_actions > 0 {
+                        via.push(format!("{} via {}", summary.external_actions, planner::Strategy::External.label()));
+                    }
+                    if summary.injected_actions > 0 {
+                        via.push(format!("{} via {}", summary.injected_actions, planner::Strategy::Injection.label()));
+                    }
+                    let via = if via.is_empty() { String::new() } else { format!(" ({})", via.join(", ")) };
+
+                    if summary.failed == 0 && summary.cancelled == 0 {
+                        self.status_msg = format!("{}{}", label, via);
+                    } else if summary.succeeded + summary.cancelled > 0 {
+                        // A job that only failed keeps the last error message
+                        self.status_msg = format!(
+                            "{}: {} succeeded, {} failed, {} cancelled{}",
+                            label, summary.succeeded, summary.failed, summary.cancelled, via
+                        );
+                    }
+                }
+            }
+        }
+        if !to_verify.is_empty() {
+            self.start_verification(to_verify);
+        }
+    }
+
+    /// Checks on a background thread that `hwnds` are really excluded from
+    /// capture. Results arrive through `verify_receiver`.
+    fn start_verification(&mut self, mut hwnds: Vec<HWND>) {
+        hwnds.sort_unstable_by_key(|h| h.0);
+        hwnds.dedup();
+        hwnds.retain(|h| !self.verifying.contains(h));
+        if hwnds.is_empty() {
+            return;
+        }
+        self.verifying.extend(&hwnds);
+
+        let sender = self.verify_sender.clone();
+        std::thread::spawn(move || {
+            // Give DWM a moment to apply a display affinity that just changed
+            std::thread::sleep(Duration::from_millis(250));
+            for hwnd in hwnds {
+                let _ = sender.send((hwnd, verify_capture_hidden(hwnd)));
+            }
+        });
+    }
+
+    fn process_verification_results(&mut self) {
+        while let Ok((hwnd, result)) = self.verify_receiver.try_recv() {
+            self.verifying.retain(|h| *h != hwnd);
+            match result {
+                Ok(verification) => {
+                    if verification.verdict == verify::Verdict::Visible {
+                        let title = self.windows.iter().find(|w| w.hwnd == hwnd).map(|w| w.title.as_str()).unwrap_or("Window");
+                        self.status_msg = format!("{} is still visible to capture", title);
+                    }
+                    self.verifications.insert(hwnd.0, verification);
+                }
+                Err(e) => {
+                    self.verifications.insert(hwnd.0, verify::Verification::inconclusive(&e));
+                }
+            }
         }
     }
 
-    fn check_for_updates(&mut self) {
+    /// Switches the update channel and checks it right away, so the dialog
+    /// and the About box show what the new channel offers.
+    fn set_update_channel(&mut self, channel: update::Channel) {
+        self.update_channel = channel;
+        self.latest_version = None;
+        self.save_current_settings();
+        self.check_for_updates(true, None);
+        self.keep_update_dialog = true;
+    }
+
+    /// Checks for a newer release on a background thread. Unless `forced`
+    /// by the user, a recent check is answered from the cache. With
+    /// `whats_new_since`, the notes of the releases after that version come
+    /// from the same release list, for the "What's New" dialog.
+    fn check_for_updates(&mut self, forced: bool, whats_new_since: Option<String>) {
         self.update_status = UpdateStatus::Checking;
         self.show_update_dialog = true;
+        self.keep_update_dialog = false;
+        if !matches!(self.installer_download, InstallerDownload::Running(_)) {
+            self.installer_download = InstallerDownload::Idle;
+        }
         let sender = self.update_sender.clone();
         let current_version = self.app_version.clone();
+        let channel = self.update_channel;
+
+        let source = self.update_source.clone();
+        let proxy = self.proxy.clone();
+        let whats_new_sender = self.whats_new_sender.clone();
+        let ctx = self.egui_ctx.clone();
 
         std::thread::spawn(move || {
-            let url = format!("https://api.github.com/repos/{}/{}/releases/latest", REPO_OWNER, REPO_NAME);
-            
-            let resp = ureq::get(&url)
-                .set("User-Agent", USER_AGENT)
-                .call();
-
-            match resp {
-                Ok(response) => {
-                    if let Ok(json) = response.into_json::<serde_json::Value>() {
-                        if let Some(tag_name) = json["tag_name"].as_str() {
-                            if is_version_newer(&current_version, tag_name) {
-                                let _ = sender.send(UpdateStatus::UpdateAvailable(tag_name.to_string()));
-                            } else {
-                                let _ = sender.send(UpdateStatus::UpToDate);
-                            }
-                            return;
-                        }
-                    }
-                    let _ = sender.send(UpdateStatus::Error("Invalid response format".to_string()));
-                },
-                Err(e) => {
-                    let _ = sender.send(UpdateStatus::Error(e.to_string()));
-                }
+            let releases = fetch_releases(&proxy, &source, forced);
+            if let Some(previous) = whats_new_since {
+                let notes = releases.clone().and_then(|releases| update::whats_new(&releases, channel, &previous, &current_version));
+                let _ = whats_new_sender.send((current_version.clone(), notes));
+                ctx.request_repaint();
             }
+            let status = match releases.and_then(|releases| update::check_releases(&releases, channel, &current_version)) {
+                Ok(update::Check::Available(available)) => UpdateStatus::UpdateAvailable(available),
+                Ok(update::Check::UpToDate) => UpdateStatus::UpToDate,
+                Err(e) => UpdateStatus::Error(e),
+            };
+            let _ = sender.send((channel, status));
+        });
+    }
+
+    /// Fetches the notes of the releases after `previous` for the "What's New"
+    /// dialog on a background thread, without checking for an update.
+    fn fetch_whats_new(&self, previous: String) {
+        let sender = self.whats_new_sender.clone();
+        let current_version = self.app_version.clone();
+        let channel = self.update_channel;
+        let source = self.update_source.clone();
+        let proxy = self.proxy.clone();
+        let ctx = self.egui_ctx.clone();
+
+        std::thread::spawn(move || {
+            let notes = fetch_releases(&proxy, &source, false)
+                .and_then(|releases| update::whats_new(&releases, channel, &previous, &current_version));
+            let _ = sender.send((current_version, notes));
+            ctx.request_repaint();
+        });
+    }
+
+    /// Downloads the installer of `release` and checks its SHA-256 on a
+    /// background thread. Progress shows in the update dialog.
+    fn download_installer(&mut self, release: update::Release) {
+        if matches!(self.installer_download, InstallerDownload::Running(_)) {
+            return;
+        }
+        self.installer_download = InstallerDownload::Running(download::Progress::default());
+        let sender = self.download_sender.clone();
+        let ctx = self.egui_ctx.clone();
+        let proxy = self.proxy.clone();
+
+        std::thread::spawn(move || {
+            let result = http_client(&proxy).and_then(|client| {
+                download::fetch_installer(&client, &release, INSTALLER_ASSET, &get_updates_dir(), |progress| {
+                    let _ = sender.send(InstallerDownload::Running(progress));
+                    ctx.request_repaint();
+                })
+            });
+            let _ = sender.send(match result {
+                Ok(installer) => InstallerDownload::Verified(installer),
+                Err(e) => InstallerDownload::Failed(e),
+            });
+            ctx.request_repaint();
         });
     }
 }
@@ -377,6 +1254,9 @@ impl WinHiderApp {
 impl eframe::App for WinHiderApp {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         let self_hwnd = get_eframe_hwnd(frame);
+        if self_hwnd.0 != 0 {
+            watch_display_changes(self_hwnd, ctx);
+        }
 
         // On first update, when we have a valid HWND, hide the app itself (capture + taskbar)
         if !self.applied_self_hide && self_hwnd.0 != 0 {
@@ -398,6 +1278,8 @@ impl eframe::App for WinHiderApp {
             if elapsed > Duration::from_secs(2) {
                 let new_windows = enumerate_windows(ctx);
                 let mut merged = Vec::new();
+                let mut auto_hide_tasks = Vec::new();
+                let mut auto_hidden = Vec::new();
                 for mut w in new_windows {
                     if let Some(old) = self.windows.iter().find(|o| o.hwnd == w.hwnd) {
                         w.is_taskbar_hidden = old.is_taskbar_hidden;
@@ -408,38 +1290,105 @@ impl eframe::App for WinHiderApp {
                             w.is_taskbar_hidden = true;
                             w.is_capture_hidden = true;
                             let pid = get_pid(w.hwnd);
-                            let _ = inject_payload(pid, InjectionAction::HideTaskbar);
-                            let _ = inject_payload(pid, InjectionAction::HideCapture);
+                            auto_hide_tasks.push(planner::InjectionTask { hwnd: w.hwnd, pid, action: InjectionAction::HideTaskbar });
+                            auto_hide_tasks.push(planner::InjectionTask { hwnd: w.hwnd, pid, action: InjectionAction::HideCapture });
+                            auto_hidden.push(w.hwnd);
                         }
                     }
                     merged.push(w);
                 }
                 self.windows = merged;
                 self.last_refresh = SystemTime::now();
+                let label = format!("Auto-hidden {} new window(s)", auto_hidden.len());
+                self.queue_injections(auto_hide_tasks, label);
+                if let Some(preview) = self.pending_preview.as_mut().filter(|_| self.dry_run) {
+                    preview.new_windows.extend(auto_hidden);
+                }
             }
         }
 
+        // Docking fires several display changes in a row; act once they settle
+        if DISPLAY_CHANGED.swap(false, Ordering::Relaxed) {
+            self.display_change_at = Some(Instant::now());
+            ctx.request_repaint_after(DISPLAY_SETTLE);
+        }
+        if self.display_change_at.is_some_and(|t| t.elapsed() >= DISPLAY_SETTLE) {
+            self.display_change_at = None;
+            self.refresh_monitors();
+        }
+
         // --- 2. WGC Frame Receiver ---
+        self.check_capture_session();
         if self.show_preview {
             if let Ok(img) = self.frame_receiver.try_recv() {
+                let img = Arc::new(img);
+                if let Some(recorder) = &mut self.recorder {
+                    recorder.push(Arc::new(rgba_frame(&img)));
+                }
                 self.preview_texture = Some(ctx.load_texture(
                     "screen_preview",
-                    img,
+                    img.clone(),
                     egui::TextureOptions::LINEAR
                 ));
+                self.last_frame = Some(img);
             }
-            ctx.request_repaint();
-        } else {
-            ctx.request_repaint_after(Duration::from_secs(1));
+            for tile in &mut self.mosaic_tiles {
+                if let Ok(img) = tile.receiver.try_recv() {
+                    tile.texture = Some(ctx.load_texture(
+                        format!("mosaic_{}", tile.number),
+                        img,
+                        egui::TextureOptions::LINEAR
+                    ));
+                }
+            }
+            if let Some(recorder) = &mut self.recorder {
+                recorder.tick(Instant::now());
+            }
+            if self.recorder.as_ref().is_some_and(|r| r.is_complete()) {
+                self.finish_recording();
+            }
+
+            let activity = ctx.input(|i| pacing::Activity::from_viewport(i.viewport().focused, i.viewport().minimized));
+            let recording_fps = self.recorder.as_ref().map(|r| r.settings().fps);
+            let fps = pacing::target_fps(self.preview_max_fps, activity, recording_fps);
+            self.preview_pace.set(pacing::interval(mosaic::per_monitor_fps(fps, self.mosaic_tiles.len())));
+
+            // New frames wake the UI themselves; a recording also needs its clock ticked
+            if let Some(recorder) = &self.recorder {
+                ctx.request_repaint_after(recorder.settings().interval());
+            }
+        }
+        ctx.request_repaint_after(Duration::from_secs(1));
+
+        // Injection results from the worker pool
+        self.process_injection_events();
+        self.process_verification_results();
+        self.process_clip_results();
+
+        while let Ok(state) = self.download_receiver.try_recv() {
+            self.installer_download = state;
+        }
+
+        if let Ok(whats_new) = self.whats_new_receiver.try_recv() {
+            // Without the notes, they are fetched again at the next start
+            if whats_new.1.is_ok() {
+                self.last_seen_version = Some(whats_new.0.clone());
+                self.save_current_settings();
+            }
+            self.whats_new = Some(whats_new);
         }
 
         // Check update results
-        if let Ok(status) = self.update_receiver.try_recv() {
+        // A result for the channel the user just switched away from is stale
+        while let Ok((channel, status)) = self.update_receiver.try_recv() {
+            if channel != self.update_channel {
+                continue;
+            }
             self.update_status = status.clone();
             // Extract latest version if available
             match &self.update_status {
-                UpdateStatus::UpdateAvailable(version) => {
-                    self.latest_version = Some(version.clone());
+                UpdateStatus::UpdateAvailable(available) => {
+                    self.latest_version = Some(available.latest.tag_name.clone());
                 }
                 UpdateStatus::UpToDate => {
                     self.latest_version = Some(self.app_version.clone());
@@ -447,7 +1396,7 @@ impl eframe::App for WinHiderApp {
                 _ => {}
             }
             // Auto-close if no update
-            if matches!(self.update_status, UpdateStatus::UpToDate | UpdateStatus::Error(_)) {
+            if matches!(self.update_status, UpdateStatus::UpToDate | UpdateStatus::Error(_)) && !self.keep_update_dialog {
                 self.show_update_dialog = false;
             }
         }
@@ -455,7 +1404,7 @@ impl eframe::App for WinHiderApp {
         // --- Hotkey Handling ---
         if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::S)) {
             if !self.selected_window_idx.is_empty() {
-                let mut success_count = 0;
+                let mut tasks = Vec::new();
                 for &selected_hwnd in &self.selected_window_idx {
                     if let Some(window) = self.windows.iter_mut().find(|w| w.hwnd == selected_hwnd) {
                         let pid = get_pid(window.hwnd);
@@ -464,17 +1413,13 @@ impl eframe::App for WinHiderApp {
                         } else { 
                             InjectionAction::HideCapture 
                         };
-                        if let Ok(_) = inject_payload(pid, action) {
-                            window.is_capture_hidden = !window.is_capture_hidden;
-                            success_count += 1;
-                        }
+                        // Reverted if the injection fails or is cancelled
+                        window.is_capture_hidden = !window.is_capture_hidden;
+                        tasks.push(planner::InjectionTask { hwnd: window.hwnd, pid, action });
                     }
                 }
-                if success_count > 0 {
-                    self.status_msg = format!("Ctrl+S: Toggled capture for {} windows", success_count);
-                } else {
-                    self.status_msg = "Ctrl+S: Failed to toggle capture".to_string();
-                }
+                let label = format!("Ctrl+S: Toggled capture for {} windows", tasks.len());
+                self.queue_injections(tasks, label);
             } else {
                 self.status_msg = "Ctrl+S: No windows selected".to_string();
             }
@@ -482,7 +1427,7 @@ impl eframe::App for WinHiderApp {
 
         if ctx.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::T)) {
             if !self.selected_window_idx.is_empty() {
-                let mut success_count = 0;
+                let mut tasks = Vec::new();
                 for &selected_hwnd in &self.selected_window_idx {
                     if let Some(window) = self.windows.iter_mut().find(|w| w.hwnd == selected_hwnd) {
                         let pid = get_pid(window.hwnd);
@@ -491,17 +1436,13 @@ impl eframe::App for WinHiderApp {
                         } else { 
                             InjectionAction::HideTaskbar 
                         };
-                        if let Ok(_) = inject_payload(pid, action) {
-                            window.is_taskbar_hidden = !window.is_taskbar_hidden;
-                            success_count += 1;
-                        }
+                        // Reverted if the injection fails or is cancelled
+                        window.is_taskbar_hidden = !window.is_taskbar_hidden;
+                        tasks.push(planner::InjectionTask { hwnd: window.hwnd, pid, action });
                     }
                 }
-                if success_count > 0 {
-                    self.status_msg = format!("Ctrl+T: Toggled taskbar for {} windows", success_count);
-                } else {
-                    self.status_msg = "Ctrl+T: Failed to toggle taskbar".to_string();
-                }
+                let label = format!("Ctrl+T: Toggled taskbar for {} windows", tasks.len());
+                self.queue_injections(tasks, label);
             } else {
                 self.status_msg = "Ctrl+T: No windows selected".to_string();
             }
@@ -511,6 +1452,10 @@ impl eframe::App for WinHiderApp {
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
+                    if !self.current_token.elevated && ui.button("Relaunch as Administrator").clicked() {
+                        self.relaunch_elevated(ctx);
+                        ui.close_menu();
+                    }
                     if ui.button("Launch CLI").clicked() {
                         if let Err(e) = launch_cli() {
                             self.status_msg = format!("Failed to launch CLI: {}", e);
@@ -519,6 +1464,10 @@ impl eframe::App for WinHiderApp {
                         }
                         ui.close_menu();
                     }
+                    if ui.button("Open Captures Folder").clicked() {
+                        let _ = std::process::Command::new("explorer").arg(get_captures_dir()).spawn();
+                        ui.close_menu();
+                    }
                     ui.separator();
                     if ui.button("Clear Temp Files").clicked() {
                         clean_temp_files();
@@ -533,32 +1482,122 @@ impl eframe::App for WinHiderApp {
 
                 ui.menu_button("Settings", |ui| {
                     if ui.checkbox(&mut self.enable_auto_update, "Enable Auto-Updates").changed() {
-                        let settings = AppSettings { 
-                            enable_auto_update: self.enable_auto_update,
-                            preview_quality: self.preview_quality,
-                        };
-                        let _ = save_settings(&settings);
+                        self.save_current_settings();
                     }
+                    ui.horizontal(|ui| {
+                        ui.label("Update Channel:");
+                        let mut channel = self.update_channel;
+                        for option in [update::Channel::Stable, update::Channel::Beta] {
+                            ui.selectable_value(&mut channel, option, option.label());
+                        }
+                        if channel != self.update_channel {
+                            self.set_update_channel(channel);
+                            ui.close_menu();
+                        }
+                    }).response.on_hover_text("Beta also offers pre-releases.");
+                    ui.menu_button("Proxy", |ui| {
+                        let mut changed = false;
+                        for mode in [proxy::Mode::System, proxy::Mode::Environment, proxy::Mode::Manual, proxy::Mode::None] {
+                            changed |= ui.selectable_value(&mut self.proxy.mode, mode, mode.label()).changed();
+                        }
+                        match self.proxy.mode {
+                            proxy::Mode::System => {
+                                ui.label(egui::RichText::new("Windows' proxy from Settings → Network & Internet → Proxy").small().weak());
+                            }
+                            proxy::Mode::Environment => {
+                                ui.label(egui::RichText::new("HTTPS_PROXY, with NO_PROXY for exceptions").small().weak());
+                            }
+                            proxy::Mode::Manual => {
+                                egui::Grid::new("proxy_settings").num_columns(2).show(ui, |ui| {
+                                    ui.label("Address:");
+                                    changed |= ui.add(egui::TextEdit::singleline(&mut self.proxy.url).hint_text("http://proxy:8080")).changed();
+                                    ui.end_row();
+                                    ui.label("User name:");
+                                    changed |= ui.add(egui::TextEdit::singleline(&mut self.proxy.username).hint_text("optional")).changed();
+                                    ui.end_row();
+                                    ui.label("Password:");
+                                    changed |= ui.add(egui::TextEdit::singleline(&mut self.proxy.password).password(true)).changed();
+                                    ui.end_row();
+                                    ui.label("Bypass:");
+                                    changed |= ui.add(egui::TextEdit::singleline(&mut self.proxy.bypass).hint_text("localhost, .corp.example.com")).changed();
+                                    ui.end_row();
+                                });
+                            }
+                            proxy::Mode::None => {}
+                        }
+                        if changed {
+                            self.save_current_settings();
+                        }
+                    }).response.on_hover_text("Used for update checks and installer downloads.");
                     
+                    if ui.checkbox(&mut self.prefer_external_strategies, "Prefer Non-Injecting Strategies").on_hover_text(
+                        "Change taskbar visibility from outside the target process. Injection is still used for capture hiding and as a fallback."
+                    ).changed() {
+                        self.injector.set_prefer_external(self.prefer_external_strategies);
+                        self.save_current_settings();
+                    }
+
+                    ui.checkbox(&mut self.dry_run, "Dry Run (Preview Before Applying)").on_hover_text(
+                        "Show which windows an action would change instead of changing them."
+                    );
+
                     ui.separator();
                     ui.label(egui::RichText::new("Preview Quality").strong());
                     
                     let quality_options = [(1, "Low (Fastest)"), (2, "Medium (Balanced)"), (3, "High (Best)")];
                     for (value, label) in quality_options.iter() {
                         if ui.selectable_value(&mut self.preview_quality, *value, *label).changed() {
-                            let settings = AppSettings { 
-                                enable_auto_update: self.enable_auto_update,
-                                preview_quality: self.preview_quality,
-                            };
-                            let _ = save_settings(&settings);
+                            self.save_current_settings();
                             self.start_capture_session();  // Restart to apply new quality
                         }
                     }
+
+                    ui.separator();
+                    ui.label(egui::RichText::new("Preview Capture").strong());
+
+                    let mut restart = false;
+                    ui.horizontal(|ui| {
+                        ui.label("Max frame rate:");
+                        let response = ui.add(egui::DragValue::new(&mut self.preview_max_fps).clamp_range(pacing::MIN_FPS..=pacing::MAX_FPS).suffix(" fps"))
+                            .on_hover_text(format!("Drops to {} fps while WinHider is in the background and {} fps while minimized", pacing::UNFOCUSED_FPS, pacing::MINIMIZED_FPS));
+                        // The pace follows the value every frame; the session only
+                        // restarts once a drag or typed value is finished
+                        restart |= response.drag_stopped() || response.lost_focus();
+                    });
+                    restart |= ui.checkbox(&mut self.preview_capture_cursor, "Capture Cursor").changed();
+                    restart |= ui.checkbox(&mut self.preview_draw_border, "Show Capture Border").on_hover_text(
+                        "The yellow frame Windows draws around a captured screen or window. Hiding it needs Windows 11."
+                    ).changed();
+                    if restart {
+                        self.save_current_settings();
+                        self.start_capture_session();
+                    }
+
+                    ui.separator();
+                    ui.label(egui::RichText::new("Clip Recording").strong());
+
+                    let mut changed = false;
+                    ui.horizontal(|ui| {
+                        ui.label("Frame rate:");
+                        changed |= ui.add(egui::DragValue::new(&mut self.clip_fps).clamp_range(1..=recording::MAX_FPS).suffix(" fps")).changed();
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Length:");
+                        changed |= ui.add(egui::DragValue::new(&mut self.clip_duration_secs).clamp_range(1..=recording::MAX_DURATION_SECS).suffix(" s")).changed();
+                    });
+                    ui.horizontal(|ui| {
+                        for format in [recording::ClipFormat::Gif, recording::ClipFormat::Apng] {
+                            changed |= ui.selectable_value(&mut self.clip_format, format, format.label()).changed();
+                        }
+                    });
+                    if changed {
+                        self.save_current_settings();
+                    }
                 });
 
                 ui.menu_button("Help", |ui| {
                     if ui.button("Check for Updates").clicked() {
-                        self.check_for_updates();
+                        self.check_for_updates(true, None);
                         ui.close_menu();
                     }
                     if ui.button("About").clicked() {
@@ -585,18 +1624,49 @@ impl eframe::App for WinHiderApp {
                         self.start_capture_session(); 
                     }
                     
-                    if !self.monitors.is_empty() {
-                         let combo = egui::ComboBox::from_id_source("monitor_select")
-                            .selected_text(format!("Monitor {}", self.selected_monitor_idx + 1))
+                    if !self.monitors.is_empty() || !self.windows.is_empty() {
+                        let combo = egui::ComboBox::from_id_source("monitor_select")
+                            .selected_text(self.preview_source_label())
                             .show_ui(ui, |ui| {
-                                for (i, _m) in self.monitors.iter().enumerate() {
-                                    if ui.selectable_value(&mut self.selected_monitor_idx, i, format!("Monitor {}", i + 1)).clicked() {
-                                        return true; 
+                                let mut changed = false;
+                                for i in 0..self.monitors.len() {
+                                    let selected = self.preview_window.is_none() && !self.preview_mosaic && self.selected_monitor_idx == i;
+                                    if ui.selectable_label(selected, self.monitor_info[i].label(i + 1)).clicked() {
+                                        self.selected_monitor_idx = i;
+                                        self.preview_window = None;
+                                        self.preview_mosaic = false;
+                                        changed = true;
+                                    }
+                                }
+                                if self.monitors.len() > 1 {
+                                    let selected = self.preview_window.is_none() && self.preview_mosaic;
+                                    if ui.selectable_label(selected, "All Monitors")
+                                        .on_hover_text("Show every monitor at once, arranged as on the desktop")
+                                        .clicked()
+                                    {
+                                        self.preview_window = None;
+                                        self.preview_mosaic = true;
+                                        changed = true;
                                     }
                                 }
-                                false
+                                if !self.windows.is_empty() {
+                                    ui.separator();
+                                    ui.label(egui::RichText::new("Windows").small().color(egui::Color32::GRAY));
+                                }
+                                for w in &self.windows {
+                                    let selected = self.preview_window == Some(w.hwnd);
+                                    if ui.selectable_label(selected, truncate_middle(&w.title, 40))
+                                        .on_hover_text("Preview this window as screen-sharing tools see it")
+                                        .clicked()
+                                    {
+                                        self.preview_window = Some(w.hwnd);
+                                        self.preview_mosaic = false;
+                                        changed = true;
+                                    }
+                                }
+                                changed
                             });
-                        
+
                         if let Some(true) = combo.inner {
                             self.start_capture_session();
                         }
@@ -606,32 +1676,145 @@ impl eframe::App for WinHiderApp {
             
             ui.separator();
 
-            if self.show_preview {
-                let default_width = ui.available_width().min(500.0);
-                // Set default height to minimum (150px) instead of full size
-                let default_height = 150.0;
-                egui::Resize::default()
-                    .default_size([default_width, default_height])
-                    .min_size([default_width, 100.0])
-                    .max_size([default_width, 600.0])
-                    .show(ui, |ui| {
-                        ui.centered_and_justified(|ui| {
-                            if let Some(texture) = &self.preview_texture {
-                                let pane_size = ui.available_size();
-                                let aspect = 16.0 / 9.0;
-                                let image_size = if pane_size.x / pane_size.y > aspect {
-                                    // Pane is wider than 16:9, fit to height
-                                    egui::vec2(pane_size.y * aspect, pane_size.y)
-                                } else {
-                                    // Pane is taller than 16:9, fit to width
-                                    egui::vec2(pane_size.x, pane_size.x / aspect)
-                                };
-                                ui.image((texture.id(), image_size));
+            // Collapsing the preview stops capture until it is expanded again
+            let preview = egui::CollapsingHeader::new("Preview")
+                .open(Some(self.show_preview))
+                .show(ui, |ui| {
+                    let default_width = ui.available_width().min(500.0);
+                    // Set default height to minimum (150px) instead of full size
+                    let default_height = 150.0;
+                    egui::Resize::default()
+                        .default_size([default_width, default_height])
+                        .min_size([default_width, 100.0])
+                        .max_size([default_width, 600.0])
+                        .show(ui, |ui| {
+                            let pane = ui.available_size();
+                            let sense = if self.show_overlay { egui::Sense::click() } else { egui::Sense::hover() };
+                            let (pane_rect, pane_response) = ui.allocate_exact_size(pane, sense);
+                            ui.painter().rect_filled(pane_rect, 0.0, ui.visuals().extreme_bg_color);
+
+                            let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+                            let tile_rects: Vec<verify::ScreenRect> = self.mosaic_tiles.iter().map(|t| t.rect).collect();
+
+                            // Where the frames go, and the screen area they show for the overlay
+                            let (fit, shown_area) = if let Some(layout) = mosaic::layout(&tile_rects, pane.x, pane.y, ctx.pixels_per_point()) {
+                                for (tile, tile_fit) in self.mosaic_tiles.iter().zip(&layout.tiles) {
+                                    let tile_rect = pane_rect_of(pane_rect, *tile_fit);
+                                    match &tile.texture {
+                                        Some(texture) => ui.painter().image(texture.id(), tile_rect, uv, egui::Color32::WHITE),
+                                        None => ui.painter().rect_filled(tile_rect, 0.0, ui.visuals().faint_bg_color),
+                                    };
+                                    ui.painter().rect_stroke(tile_rect, 0.0, egui::Stroke::new(1.0, ui.visuals().weak_text_color()));
+                                    ui.painter().text(
+                                        tile_rect.left_top() + egui::vec2(4.0, 2.0),
+                                        egui::Align2::LEFT_TOP,
+                                        tile.number.to_string(),
+                                        egui::FontId::proportional(11.0),
+                                        egui::Color32::WHITE,
+                                    );
+                                }
+                                (layout.image, Some(layout.desktop))
                             } else {
-                                ui.label("Waiting for WGC Stream...");
+                                // Real frame size; the monitor's mode until the first frame arrives
+                                let (content_w, content_h) = match &self.preview_texture {
+                                    Some(texture) => (texture.size()[0] as u32, texture.size()[1] as u32),
+                                    None => self.preview_source_size(),
+                                };
+                                let fit = letterbox::letterbox(content_w, content_h, pane.x, pane.y, ctx.pixels_per_point());
+                                let image_rect = pane_rect_of(pane_rect, fit);
+
+                                if let Some(texture) = &self.preview_texture {
+                                    ui.painter().image(texture.id(), image_rect, uv, egui::Color32::WHITE);
+                                } else {
+                                    ui.painter().rect_filled(image_rect, 0.0, ui.visuals().faint_bg_color);
+                                    ui.painter().text(
+                                        image_rect.center(),
+                                        egui::Align2::CENTER_CENTER,
+                                        "Waiting for WGC Stream...",
+                                        egui::FontId::default(),
+                                        ui.visuals().weak_text_color(),
+                                    );
+                                }
+                                let monitor = self.monitors.get(self.selected_monitor_idx)
+                                    .filter(|_| self.preview_window.is_none() && self.preview_texture.is_some())
+                                    .and_then(|m| monitor_rect(HMONITOR(m.as_raw_hmonitor() as isize)));
+                                (fit, monitor)
+                            };
+
+                            if let Some(area) = shown_area.filter(|_| self.show_overlay) {
+                                let outlines = self.hidden_window_outlines(area, fit);
+                                let hovered = pane_response.hover_pos()
+                                    .and_then(|p| overlay::hit_test(&outlines, p.x - pane_rect.min.x, p.y - pane_rect.min.y));
+                                let painter = ui.painter_at(pane_rect_of(pane_rect, fit));
+
+                                // Bottom first, so the topmost window's label ends up on top
+                                for (i, outline) in outlines.iter().enumerate().rev() {
+                                    let rect = pane_rect_of(pane_rect, outline.rect);
+                                    let color = if self.selected_window_idx.contains(&HWND(outline.hwnd)) {
+                                        egui::Color32::YELLOW
+                                    } else {
+                                        egui::Color32::from_rgb(255, 110, 90)
+                                    };
+                                    let width = if hovered == Some(i) { 2.5 } else { 1.5 };
+                                    painter.rect_stroke(rect, 0.0, egui::Stroke::new(width, color));
+
+                                    let galley = painter.layout_no_wrap(outline.label.clone(), egui::FontId::proportional(11.0), egui::Color32::BLACK);
+                                    let label_rect = egui::Rect::from_min_size(rect.min, galley.size() + egui::vec2(6.0, 2.0));
+                                    painter.rect_filled(label_rect, 0.0, color);
+                                    painter.galley(label_rect.min + egui::vec2(3.0, 1.0), galley, egui::Color32::BLACK);
+                                }
+
+                                if let Some(i) = hovered {
+                                    if pane_response.clicked() {
+                                        let toggle = ui.input(|i| i.modifiers.ctrl);
+                                        self.select_from_preview(HWND(outlines[i].hwnd), toggle);
+                                    }
+                                    pane_response.on_hover_text(format!("{}\nClick to select, Ctrl+click to add", outlines[i].label));
+                                }
                             }
                         });
+
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(self.last_frame.is_some(), egui::Button::new("📷 Save Snapshot"))
+                            .on_hover_text("Save the current preview frame as PNG")
+                            .clicked()
+                        {
+                            self.save_snapshot();
+                        }
+
+                        if let Some((done, total)) = self.recorder.as_ref().map(|r| r.progress()) {
+                            if ui.button("⏹ Stop").clicked() {
+                                self.finish_recording();
+                            }
+                            ui.add(egui::ProgressBar::new(done as f32 / total.max(1) as f32)
+                                .desired_width(120.0)
+                                .text(format!("{}/{}", done, total)));
+                        } else if self.clip_saving {
+                            ui.spinner();
+                            ui.label(egui::RichText::new("Encoding clip...").small().color(egui::Color32::GRAY));
+                        } else if ui.add_enabled(self.mosaic_tiles.is_empty(), egui::Button::new(format!("⏺ Record {}s {}", self.clip_duration_secs, self.clip_format.label())))
+                            .on_hover_text("Record what the audience sees. Frame rate, length and format are under Settings.")
+                            .on_disabled_hover_text("Pick a single monitor or window to record")
+                            .clicked()
+                        {
+                            self.start_recording();
+                        }
+
+                        ui.separator();
+                        if ui.add_enabled(self.preview_window.is_none(), egui::Checkbox::new(&mut self.show_overlay, "Outline Hidden Windows"))
+                            .on_hover_text("Mark where hidden windows are on this monitor. Click an outline to select the window.")
+                            .on_disabled_hover_text("Outlines are drawn on monitor previews")
+                            .changed()
+                        {
+                            self.save_current_settings();
+                        }
                     });
+                });
+            if preview.header_response.clicked() {
+                self.show_preview = !self.show_preview;
+                self.start_capture_session();
+            }
+            if self.show_preview {
                 ui.add_space(10.0);
             }
 
@@ -642,9 +1825,26 @@ impl eframe::App for WinHiderApp {
                     ui.label(egui::RichText::new("Auto-Hide on Start").strong());
                     ui.vertical(|ui| {
                         ui.label(format!("{} apps configured", self.auto_hide_list.len()));
-                        if ui.button("Edit List").clicked() {
-                            self.show_auto_hide_editor = true;
-                        }
+                        ui.horizontal(|ui| {
+                            if ui.button("Edit List").clicked() {
+                                self.show_auto_hide_editor = true;
+                            }
+                            if ui.button("Preview Rules").on_hover_text("Dry run the auto-hide list against all open windows").clicked() {
+                                let tasks: Vec<planner::InjectionTask> = self.windows.iter()
+                                    .filter(|w| self.should_auto_hide(&w.title))
+                                    .flat_map(|w| {
+                                        let pid = get_pid(w.hwnd);
+                                        [InjectionAction::HideTaskbar, InjectionAction::HideCapture]
+                                            .map(|action| planner::InjectionTask { hwnd: w.hwnd, pid, action })
+                                    })
+                                    .collect();
+                                if tasks.is_empty() {
+                                    self.status_msg = "Dry run: no open window matches the auto-hide list".to_string();
+                                } else {
+                                    self.open_preview(tasks, "Auto-hide rules".to_string());
+                                }
+                            }
+                        });
                     });
                 });
 
@@ -667,7 +1867,7 @@ impl eframe::App for WinHiderApp {
                 ui.label(egui::RichText::new("Target Applications").strong());
                 if ui.button("🔄 Force Refresh").clicked() {
                     self.windows = enumerate_windows(ctx);
-                    self.monitors = Monitor::enumerate().unwrap_or_default();
+                    self.refresh_monitors();
                     self.last_refresh = SystemTime::now();
                     self.selected_window_idx.clear(); // Reset selection on refresh
                     self.status_msg = "List refreshed.".to_string();
@@ -676,9 +1876,30 @@ impl eframe::App for WinHiderApp {
 
             ui.add_space(5.0);
             ui.label(egui::RichText::new(&self.status_msg).color(egui::Color32::LIGHT_BLUE));
+            if !self.active_jobs.is_empty() {
+                let total: usize = self.active_jobs.iter().map(|(job, _)| job.total()).sum();
+                let completed: usize = self.active_jobs.iter().map(|(job, _)| job.completed()).sum();
+                let cancelling = self.active_jobs.iter().all(|(job, _)| job.is_cancelled());
+                ui.horizontal(|ui| {
+                    ui.add(egui::ProgressBar::new(completed as f32 / total.max(1) as f32)
+                        .desired_width(200.0)
+                        .text(format!("{}/{}", completed, total)));
+                    if cancelling {
+                        ui.label(egui::RichText::new("Cancelling...").small().color(egui::Color32::GRAY));
+                    } else if ui.button("Cancel").clicked() {
+                        for (job, _) in &self.active_jobs {
+                            job.cancel();
+                        }
+                    }
+                });
+            }
             ui.label(egui::RichText::new("Hotkeys: Ctrl+S=Toggle Capture, Ctrl+T=Toggle Taskbar (select windows first, Ctrl+click for multi-select)").small().color(egui::Color32::GRAY));
             ui.separator();
 
+            // Checkbox changes are queued after the list is drawn
+            let mut pending_job: Option<(planner::InjectionTask, String)> = None;
+            let mut pending_verify: Option<HWND> = None;
+
             egui::ScrollArea::vertical()
                 .auto_shrink([false, false]) 
                 .show(ui, |ui| {
@@ -723,8 +1944,26 @@ impl eframe::App for WinHiderApp {
                                     },
                                 );
 
+                                // Part C: Integrity level of the owning process
+                                if let Some(token) = &window.token {
+                                    job.append(
+                                        &format!(" [{}]", token.label()),
+                                        0.0,
+                                        egui::TextFormat {
+                                            font_id: egui::FontId::monospace(11.0),
+                                            color: egui::Color32::DARK_GRAY,
+                                            ..Default::default()
+                                        },
+                                    );
+                                }
+
                                 // 3. Render Selectable Label with Mixed Text
-                                if ui.selectable_label(is_selected, job).clicked() {
+                                let row = ui.selectable_label(is_selected, job);
+                                if self.scroll_to_window == Some(window.hwnd) {
+                                    row.scroll_to_me(Some(egui::Align::Center));
+                                    self.scroll_to_window = None;
+                                }
+                                if row.clicked() {
                                     // Handle Multi-selection (Ctrl+Click) vs Single Selection
                                     if ui.input(|i| i.modifiers.ctrl) {
                                         if is_selected {
@@ -747,9 +1986,28 @@ impl eframe::App for WinHiderApp {
                                 if is_selected {
                                     ui.label(egui::RichText::new("← Selected").small().color(egui::Color32::YELLOW));
                                 }
+
+                                // 5. Elevation Badge
+                                if window.requires_admin {
+                                    ui.label(egui::RichText::new("requires admin").small().color(egui::Color32::LIGHT_RED))
+                                        .on_hover_text("This process runs at a higher integrity level. Use File > Relaunch as Administrator.");
+                                }
+
+                                // 6. Capture Verification Badge
+                                if self.verifying.contains(&window.hwnd) {
+                                    ui.spinner();
+                                } else if let Some(v) = self.verifications.get(&window.hwnd.0) {
+                                    let color = match v.verdict {
+                                        verify::Verdict::Hidden => egui::Color32::GREEN,
+                                        verify::Verdict::Visible => egui::Color32::LIGHT_RED,
+                                        verify::Verdict::Inconclusive => egui::Color32::GRAY,
+                                    };
+                                    ui.label(egui::RichText::new(v.verdict.label()).small().color(color))
+                                        .on_hover_text(&v.detail);
+                                }
                             });
 
-                            ui.horizontal(|ui| {
+                            ui.add_enabled_ui(!window.requires_admin, |ui| ui.horizontal(|ui| {
                                 if ui.checkbox(&mut window.is_taskbar_hidden, "Hide Taskbar").changed() {
                                     let pid = get_pid(window.hwnd);
                                     let action = if window.is_taskbar_hidden { 
@@ -757,10 +2015,10 @@ impl eframe::App for WinHiderApp {
                                     } else { 
                                         InjectionAction::ShowTaskbar 
                                     };
-                                    if let Err(e) = inject_payload(pid, action) {
-                                        self.status_msg = format!("Error: {}", e);
-                                        window.is_taskbar_hidden = !window.is_taskbar_hidden;
-                                    }
+                                    pending_job = Some((
+                                        planner::InjectionTask { hwnd: window.hwnd, pid, action },
+                                        format!("Taskbar state updated: {}", window.title),
+                                    ));
                                 }
 
                                 ui.separator();
@@ -772,24 +2030,39 @@ impl eframe::App for WinHiderApp {
                                     } else {
                                         InjectionAction::ShowCapture
                                     };
-                                    match inject_payload(pid, action) {
-                                        Ok(_) => self.status_msg = format!("Capture state updated: {}", window.title),
-                                        Err(e) => {
-                                            self.status_msg = format!("Error: {}", e);
-                                            window.is_capture_hidden = !window.is_capture_hidden;
-                                        }
-                                    }
+                                    pending_job = Some((
+                                        planner::InjectionTask { hwnd: window.hwnd, pid, action },
+                                        format!("Capture state updated: {}", window.title),
+                                    ));
                                 }
-                            });
+
+                                if window.is_capture_hidden && ui.small_button("Verify")
+                                    .on_hover_text("Compare a fresh capture of the screen with the window's content")
+                                    .clicked()
+                                {
+                                    pending_verify = Some(window.hwnd);
+                                }
+                            }));
                         });
                     }
                 });
+
+            if let Some((task, label)) = pending_job {
+                self.queue_injections(vec![task], label);
+            }
+            if let Some(hwnd) = pending_verify {
+                self.verifications.remove(&hwnd.0);
+                self.start_verification(vec![hwnd]);
+            }
         });
 
         // --- UPDATE DIALOG ---
         if self.show_update_dialog {
             let mut is_open = true;
             let mut should_close = false;
+            let mut channel = self.update_channel;
+            let mut start_download = None;
+            let mut run_installer = None;
 
             egui::Window::new("Check for Updates")
                 .collapsible(false)
@@ -801,7 +2074,7 @@ impl eframe::App for WinHiderApp {
                         match &self.update_status {
                             UpdateStatus::Checking => {
                                 ui.spinner();
-                                ui.label("Checking GitHub for updates...");
+                                ui.label(format!("Checking {} for {} updates...", self.update_source.describe(), channel.label().to_lowercase()));
                             },
                             UpdateStatus::UpToDate => {
                                 ui.label(egui::RichText::new("✓ You are up to date!").color(egui::Color32::GREEN));
@@ -810,18 +2083,52 @@ impl eframe::App for WinHiderApp {
                                     should_close = true;
                                 }
                             },
-                            UpdateStatus::UpdateAvailable(new_tag) => {
+                            UpdateStatus::UpdateAvailable(available) => {
+                                let release = &available.latest;
                                 ui.heading(egui::RichText::new("New Version Available!").color(egui::Color32::YELLOW));
                                 ui.label(format!("Current: {}", self.app_version));
-                                ui.label(format!("Latest:  {}", new_tag));
+                                ui.label(format!("Latest:  {}", release.tag_name));
+                                ui.add_space(6.0);
+                                egui::CollapsingHeader::new(format!("What's new ({} releases)", available.changes.len()))
+                                    .default_open(true)
+                                    .show(ui, |ui| release_notes_ui(ui, &mut self.markdown_cache, &available.changes));
                                 ui.add_space(10.0);
                                 
-                                if ui.button(egui::RichText::new("⬇ Download Installer").size(16.0)).clicked() {
-                                    let download_url = format!(
-                                        "https://github.com/{}/{}/releases/download/{}/WinhiderInstaller.exe",
-                                        REPO_OWNER, REPO_NAME, new_tag
-                                    );
-                                    let _ = std::process::Command::new("cmd").args(["/C", "start", &download_url]).spawn();
+                                match &self.installer_download {
+                                    InstallerDownload::Idle | InstallerDownload::Failed(_) => {
+                                        if let InstallerDownload::Failed(err) = &self.installer_download {
+                                            ui.label(egui::RichText::new(format!("⚠ {}", err)).color(egui::Color32::RED));
+                                        }
+                                        if release.asset(INSTALLER_ASSET).is_some() {
+                                            let label = if matches!(self.installer_download, InstallerDownload::Failed(_)) {
+                                                "⟳ Retry Download"
+                                            } else {
+                                                "⬇ Download Installer"
+                                            };
+                                            if ui.button(egui::RichText::new(label).size(16.0)).clicked() {
+                                                start_download = Some(release.clone());
+                                            }
+                                        } else if let Some(url) = &release.html_url {
+                                            // A manifest may only link the release page
+                                            if ui.button(egui::RichText::new("🌐 Open Release Page").size(16.0)).clicked() {
+                                                let _ = std::process::Command::new("cmd").args(["/C", "start", url]).spawn();
+                                            }
+                                        }
+                                    },
+                                    InstallerDownload::Running(progress) => {
+                                        let mb = |bytes: u64| bytes as f64 / 1_000_000.0;
+                                        let text = match progress.total {
+                                            Some(total) => format!("{:.1} of {:.1} MB", mb(progress.received), mb(total)),
+                                            None => format!("{:.1} MB", mb(progress.received)),
+                                        };
+                                        ui.add(egui::ProgressBar::new(progress.fraction().unwrap_or(0.0)).text(text).animate(true));
+                                    },
+                                    InstallerDownload::Verified(installer) => {
+                                        ui.label(egui::RichText::new("✓ Installer downloaded and verified (SHA-256)").color(egui::Color32::GREEN));
+                                        if ui.button(egui::RichText::new("▶ Run Installer").size(16.0)).clicked() {
+                                            run_installer = Some(installer.clone());
+                                        }
+                                    },
                                 }
                             },
                             UpdateStatus::Error(err) => {
@@ -833,11 +2140,89 @@ impl eframe::App for WinHiderApp {
                             },
                             _ => {}
                         }
+
+                        ui.add_space(6.0);
+                        ui.horizontal(|ui| {
+                            ui.label("Channel:");
+                            for option in [update::Channel::Stable, update::Channel::Beta] {
+                                ui.selectable_value(&mut channel, option, option.label());
+                            }
+                        });
                     });
                 });
             
             if !is_open || should_close {
                 self.show_update_dialog = false;
+            } else if channel != self.update_channel {
+                self.set_update_channel(channel);
+            }
+            if let Some(release) = start_download {
+                self.download_installer(release);
+            }
+            if let Some(installer) = run_installer {
+                // Closing lets the installer replace our files
+                match launch_installer(&installer) {
+                    Ok(()) => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+                    Err(e) => self.installer_download = InstallerDownload::Failed(e),
+                }
+            }
+        }
+
+        // --- WHAT'S NEW DIALOG ---
+        if let Some((version, notes)) = &self.whats_new {
+            let mut is_open = true;
+            let mut should_close = false;
+
+            egui::Window::new(format!("What's New in {}", version))
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+                .open(&mut is_open)
+                .show(ctx, |ui| {
+                    match notes {
+                        Ok(releases) if !releases.is_empty() => release_notes_ui(ui, &mut self.markdown_cache, releases),
+                        Ok(_) => {
+                            ui.label("No release notes were published for this version.");
+                        }
+                        Err(e) => {
+                            ui.label(egui::RichText::new(format!("⚠ Could not load the release notes: {}", e)).color(egui::Color32::RED));
+                        }
+                    }
+                    ui.add_space(6.0);
+                    ui.vertical_centered(|ui| {
+                        if ui.button("Close").clicked() {
+                            should_close = true;
+                        }
+                    });
+                });
+
+            if !is_open || should_close {
+                self.whats_new = None;
+            }
+        }
+
+        // --- SETTINGS NOTICE ---
+        if let Some(notice) = &self.settings_notice {
+            let mut is_open = true;
+            let mut should_close = false;
+
+            egui::Window::new("Settings Repaired")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+                .open(&mut is_open)
+                .show(ctx, |ui| {
+                    ui.label(notice);
+                    ui.add_space(6.0);
+                    ui.vertical_centered(|ui| {
+                        if ui.button("OK").clicked() {
+                            should_close = true;
+                        }
+                    });
+                });
+
+            if !is_open || should_close {
+                self.settings_notice = None;
             }
         }
 
@@ -874,10 +2259,15 @@ impl eframe::App for WinHiderApp {
                                 .size(13.0)
                                 .color(egui::Color32::GRAY),
                         );
+                        ui.label(
+                            egui::RichText::new(format!("Build {} · {}", BUILD_COMMIT, BUILD_DATE))
+                                .size(11.0)
+                                .color(egui::Color32::GRAY),
+                        );
 
                         // Latest Available Version
                         if let Some(latest) = &self.latest_version {
-                            let color = if is_version_newer(&self.app_version, latest) {
+                            let color = if semver::is_newer(&self.app_version, latest).unwrap_or(false) {
                                 egui::Color32::from_rgb(255, 165, 0) // Orange for update available
                             } else {
                                 egui::Color32::GREEN // Green for up to date
@@ -1027,33 +2417,83 @@ impl eframe::App for WinHiderApp {
             }
         }
 
-    }
-}
+        // --- DRY RUN PREVIEW DIALOG ---
+        if let Some(preview) = &self.pending_preview {
+            let mut is_open = true;
+            let mut apply = false;
+            let mut discard = false;
 
-// ===============================
-// UTILITIES (Helpers)
-// ===============================
+            egui::Window::new("Dry Run Preview")
+                .collapsible(false)
+                .resizable(true)
+                .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+                .default_width(480.0)
+                .open(&mut is_open)
+                .show(ctx, |ui| {
+                    ui.label(egui::RichText::new(&preview.label).strong());
+                    ui.add_space(5.0);
 
-fn is_version_newer(current: &str, new: &str) -> bool {
-    let parse_version = |v: &str| -> Vec<u32> {
-        v.trim_start_matches('v')
-            .split('.')
-            .map(|s| s.parse::<u32>().unwrap_or(0))
-            .collect()
-    };
+                    if preview.diff.is_empty() {
+                        ui.label("No window would change.");
+                    } else {
+                        let describe = |change: &Option<dryrun::StateChange>| match change {
+                            Some(c) => format!(
+                                "{} → {} ({})",
+                                if c.current { "hidden" } else { "visible" },
+                                if c.intended { "hidden" } else { "visible" },
+                                c.strategy.label()
+                            ),
+                            None => "—".to_string(),
+                        };
+                        egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                            egui::Grid::new("dry_run_grid").striped(true).num_columns(3).show(ui, |ui| {
+                                ui.label(egui::RichText::new("Window").strong());
+                                ui.label(egui::RichText::new("Capture").strong());
+                                ui.label(egui::RichText::new("Taskbar").strong());
+                                ui.end_row();
+                                for d in &preview.diff {
+                                    ui.label(format!("{} ({})", truncate_middle(&d.title, 35), d.pid));
+                                    ui.label(describe(&d.capture));
+                                    ui.label(describe(&d.taskbar));
+                                    ui.end_row();
+                                }
+                            });
+                        });
+                    }
+
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("Apply").clicked() {
+                            apply = true;
+                        }
+                        if ui.button("Discard").clicked() {
+                            discard = true;
+                        }
+                    });
+                });
 
-    let curr_parts = parse_version(current);
-    let new_parts = parse_version(new);
+            if apply {
+                if let Some(preview) = self.pending_preview.take() {
+                    self.apply_task_flags(&preview.tasks);
+                    let job = self.injector.submit(preview.tasks);
+                    self.status_msg = format!("Working on {} process(es)...", job.total());
+                    self.active_jobs.push((job, preview.label));
+                }
+            } else if discard || !is_open {
+                if let Some(preview) = self.pending_preview.take() {
+                    self.windows.retain(|w| !preview.new_windows.contains(&w.hwnd));
+                }
+                self.status_msg = "Dry run discarded; nothing was changed.".to_string();
+            }
+        }
 
-    for i in 0..std::cmp::max(curr_parts.len(), new_parts.len()) {
-        let c = *curr_parts.get(i).unwrap_or(&0);
-        let n = *new_parts.get(i).unwrap_or(&0);
-        if n > c { return true; }
-        if n < c { return false; }
     }
-    false
 }
 
+// ===============================
+// UTILITIES (Helpers)
+// ===============================
+
 #[allow(unused_must_use)]
 fn get_window_icon(hwnd: HWND, _ctx: &egui::Context) -> Option<egui::TextureHandle> {
     unsafe {
@@ -1121,13 +2561,19 @@ fn get_window_icon(hwnd: HWND, _ctx: &egui::Context) -> Option<egui::TextureHand
     }
 }
 
+static TEMP_DLL_SEQ: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// Injects the payload once into `target_pid`, applying every action in
+/// `actions` to all windows of that process.
 #[allow(unused_must_use)]
-fn inject_payload(target_pid: u32, action: InjectionAction) -> std::result::Result<String, String> {
+fn inject_payload(target_pid: u32, actions: &[InjectionAction]) -> std::result::Result<String, String> {
+    if actions.is_empty() { return Err("No actions to apply".to_string()); }
+
     unsafe {
         let mut master_dll_path = std::env::current_exe()
             .map_err(|e| e.to_string())?
             .parent()
-            .unwrap()
+            .ok_or("Failed to get exe directory")?
             .join("lgpllibs.dll");
         
         if !master_dll_path.exists() {
@@ -1136,27 +2582,31 @@ fn inject_payload(target_pid: u32, action: InjectionAction) -> std::result::Resu
              }
         }
         if !master_dll_path.exists() { return Err("Base DLL not found".to_string()); }
+        if elevation::pid_requires_admin(target_pid) { return Err(elevation::ELEVATION_REQUIRED.to_string()); }
 
         let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
-        let keyword = match action {
+        // The payload reads every keyword present in its own filename
+        let keywords: Vec<&str> = actions.iter().map(|action| match action {
             InjectionAction::HideCapture => "mozavcodec",
             InjectionAction::ShowCapture => "mozavutil",
             InjectionAction::HideTaskbar => "gkcodecs",
             InjectionAction::ShowTaskbar => "freebl3",
-        };
+        }).collect();
 
-        let new_filename = format!("{}_{}.dll", keyword, timestamp);
+        // Workers may inject concurrently, so the timestamp alone is not unique
+        let seq = TEMP_DLL_SEQ.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let new_filename = format!("{}_{}_{}.dll", keywords.join("_"), timestamp, seq);
         // Place temporary DLLs in AppData\<APP_NAME> instead of the application folder
         let config_dir = get_config_dir();
         let target_dll_path = config_dir.join(&new_filename);
 
         if let Err(e) = std::fs::copy(&master_dll_path, &target_dll_path) {
-            return Err(format!("Failed to create temp DLL: {}", e));
+            return Err(format!("Failed to create temp DLL {}: {}", paths::display(&target_dll_path), e));
         }
 
-        let path_str = target_dll_path.to_str().unwrap();
-        let mut path_bytes: Vec<u8> = path_str.bytes().collect();
-        path_bytes.push(0); 
+        // UTF-16 all the way: non-ASCII profile names and non-Unicode paths stay intact
+        let path_wide = paths::loader_path(&target_dll_path);
+        let path_size = path_wide.len() * std::mem::size_of::<u16>();
 
         let process = OpenProcess(
             PROCESS_CREATE_THREAD | PROCESS_QUERY_INFORMATION | PROCESS_VM_OPERATION | PROCESS_VM_WRITE | PROCESS_VM_READ,
@@ -1164,17 +2614,17 @@ fn inject_payload(target_pid: u32, action: InjectionAction) -> std::result::Resu
             target_pid
         ).map_err(|e| format!("OpenProcess failed: {}", e))?;
 
-        let remote_mem = VirtualAllocEx(process, None, path_bytes.len(), MEM_COMMIT | MEM_RESERVE, PAGE_READWRITE);
+        let remote_mem = VirtualAllocEx(process, None, path_size, MEM_COMMIT | MEM_RESERVE, PAGE_READWRITE);
         if remote_mem.is_null() { let _ = CloseHandle(process); return Err("Alloc fail".to_string()); }
 
         let mut written = 0;
-        let write_res = WriteProcessMemory(process, remote_mem, path_bytes.as_ptr() as *const c_void, path_bytes.len(), Some(&mut written));
+        let write_res = WriteProcessMemory(process, remote_mem, path_wide.as_ptr() as *const c_void, path_size, Some(&mut written));
         if write_res.is_err() { let _ = VirtualFreeEx(process, remote_mem, 0, MEM_RELEASE); let _ = CloseHandle(process); return Err("Write fail".to_string()); }
 
         let kernel32 = GetModuleHandleA(s!("kernel32.dll")).unwrap();
-        let load_lib = GetProcAddress(kernel32, s!("LoadLibraryA"));
+        let load_lib = GetProcAddress(kernel32, s!("LoadLibraryW"));
         
-        if load_lib.is_none() { let _ = VirtualFreeEx(process, remote_mem, 0, MEM_RELEASE); let _ = CloseHandle(process); return Err("No LoadLibraryA".to_string()); }
+        if load_lib.is_none() { let _ = VirtualFreeEx(process, remote_mem, 0, MEM_RELEASE); let _ = CloseHandle(process); return Err("No LoadLibraryW".to_string()); }
 
         let start_routine = std::mem::transmute::<unsafe extern "system" fn() -> isize, unsafe extern "system" fn(*mut c_void) -> u32>(std::mem::transmute(load_lib));
 
@@ -1353,6 +2803,252 @@ fn set_taskbar_visibility_external(hwnd: HWND, hide: bool) -> std::result::Resul
     Ok(())
 }
 
+/// Actual (capture hidden, taskbar hidden) state of a window, read from the
+/// outside: display affinity and the extended window style.
+fn query_window_state(hwnd: HWND) -> (bool, bool) {
+    unsafe {
+        let mut affinity = 0u32;
+        let capture_hidden = GetWindowDisplayAffinity(hwnd, &mut affinity).is_ok() && affinity != WDA_NONE.0;
+        let style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE) as u32;
+        let taskbar_hidden = style & WS_EX_TOOLWINDOW.0 != 0 && style & WS_EX_APPWINDOW.0 == 0;
+        (capture_hidden, taskbar_hidden)
+    }
+}
+
+/// Checks that `hwnd` is really excluded from capture: grabs one frame of its
+/// monitor and compares it with what the window draws. Blocks for up to a
+/// few seconds, so the GUI runs it off the UI thread.
+fn verify_capture_hidden(hwnd: HWND) -> std::result::Result<verify::Verification, String> {
+    unsafe {
+        if !IsWindow(hwnd).as_bool() {
+            return Err("Window no longer exists".to_string());
+        }
+        if IsIconic(hwnd).as_bool() {
+            return Ok(verify::Verification::inconclusive("window is minimized"));
+        }
+
+        let mut rect = RECT::default();
+        GetWindowRect(hwnd, &mut rect).map_err(|e| e.to_string())?;
+        let window = verify::ScreenRect { left: rect.left, top: rect.top, right: rect.right, bottom: rect.bottom };
+
+        let hmonitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+        let monitor = monitor_rect(hmonitor).ok_or("Could not query the window's monitor")?;
+
+        let rendering = render_window(hwnd, window.width(), window.height())?;
+        let captured = capture_one_frame(Monitor::from_raw_hmonitor(hmonitor.0 as *mut c_void))?;
+
+        verify::compare(
+            &verify::RgbaView { pixels: &captured.pixels, width: captured.width, height: captured.height },
+            monitor,
+            &verify::RgbaView { pixels: &rendering, width: window.width() as u32, height: window.height() as u32 },
+            window,
+            // Points where another window is on top say nothing about this one
+            |x, y| GetAncestor(WindowFromPoint(POINT { x, y }), GA_ROOT) == hwnd,
+        )
+    }
+}
+
+/// `fit`, given relative to the preview pane, as an absolute egui rectangle.
+fn pane_rect_of(pane: egui::Rect, fit: letterbox::FitRect) -> egui::Rect {
+    egui::Rect::from_min_size(pane.min + egui::vec2(fit.x, fit.y), egui::vec2(fit.width, fit.height))
+}
+
+/// Release notes of `releases`, newest first, rendered from Markdown.
+fn release_notes_ui(ui: &mut egui::Ui, cache: &mut CommonMarkCache, releases: &[update::Release]) {
+    ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
+        ui.set_max_width(420.0);
+        egui::ScrollArea::vertical().max_height(280.0).show(ui, |ui| {
+            for (i, release) in releases.iter().enumerate() {
+                if i > 0 {
+                    ui.separator();
+                }
+                let title = match release.title() {
+                    title if title == release.tag_name => title.to_string(),
+                    title => format!("{} - {}", release.tag_name, title),
+                };
+                ui.label(egui::RichText::new(title).strong().size(15.0));
+                // Only the date of an RFC 3339 time
+                if let Some(date) = release.published_at.as_deref().and_then(|t| t.get(..10)) {
+                    ui.label(egui::RichText::new(date).small().color(egui::Color32::GRAY));
+                }
+                match release.body.as_deref().map(str::trim).filter(|body| !body.is_empty()) {
+                    Some(body) => {
+                        CommonMarkViewer::new(("release_notes", &release.tag_name)).show(ui, cache, body);
+                    }
+                    None => {
+                        ui.label(egui::RichText::new("No release notes.").italics().color(egui::Color32::GRAY));
+                    }
+                }
+            }
+        });
+    });
+}
+
+/// Monitors that can be described, with their descriptions.
+fn enumerate_monitors() -> (Vec<Monitor>, Vec<displays::DisplayInfo>) {
+    Monitor::enumerate().unwrap_or_default()
+        .into_iter()
+        .filter_map(|m| Some((m, describe_monitor(&m)?)))
+        .unzip()
+}
+
+fn describe_monitor(monitor: &Monitor) -> Option<displays::DisplayInfo> {
+    let hmonitor = HMONITOR(monitor.as_raw_hmonitor() as isize);
+    let mut info = MONITORINFO { cbSize: std::mem::size_of::<MONITORINFO>() as u32, ..Default::default() };
+    if !unsafe { GetMonitorInfoW(hmonitor, &mut info) }.as_bool() {
+        return None;
+    }
+    let (mut dpi_x, mut dpi_y) = (96, 96);
+    let _ = unsafe { GetDpiForMonitor(hmonitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) };
+
+    let m = info.rcMonitor;
+    Some(displays::DisplayInfo {
+        device_name: monitor.device_name().unwrap_or_default(),
+        friendly_name: monitor.name().unwrap_or_default(),
+        rect: verify::ScreenRect { left: m.left, top: m.top, right: m.right, bottom: m.bottom },
+        primary: info.dwFlags & MONITORINFOF_PRIMARY != 0,
+        scale_percent: displays::scale_percent(dpi_x),
+    })
+}
+
+/// How long display changes must be quiet before monitors are re-enumerated.
+const DISPLAY_SETTLE: Duration = Duration::from_millis(500);
+
+static DISPLAY_CHANGED: AtomicBool = AtomicBool::new(false);
+static DISPLAY_WATCH_CTX: OnceLock<egui::Context> = OnceLock::new();
+
+/// Subclasses the main window to hear about monitors being added, removed,
+/// rearranged or rescaled. Only the first call does anything.
+fn watch_display_changes(hwnd: HWND, ctx: &egui::Context) {
+    if DISPLAY_WATCH_CTX.set(ctx.clone()).is_ok() {
+        unsafe {
+            let _ = SetWindowSubclass(hwnd, Some(display_watch_proc), 1, 0);
+        }
+    }
+}
+
+unsafe extern "system" fn display_watch_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM, _id: usize, _data: usize) -> LRESULT {
+    if msg == WM_DISPLAYCHANGE || msg == WM_DPICHANGED {
+        DISPLAY_CHANGED.store(true, Ordering::Relaxed);
+        if let Some(ctx) = DISPLAY_WATCH_CTX.get() {
+            ctx.request_repaint();
+        }
+    }
+    unsafe { DefSubclassProc(hwnd, msg, wparam, lparam) }
+}
+
+/// Bounds of a monitor in physical screen pixels.
+fn monitor_rect(hmonitor: HMONITOR) -> Option<verify::ScreenRect> {
+    let mut info = MONITORINFO { cbSize: std::mem::size_of::<MONITORINFO>() as u32, ..Default::default() };
+    if !unsafe { GetMonitorInfoW(hmonitor, &mut info) }.as_bool() {
+        return None;
+    }
+    let m = info.rcMonitor;
+    Some(verify::ScreenRect { left: m.left, top: m.top, right: m.right, bottom: m.bottom })
+}
+
+/// The window drawn by itself into a bitmap, as tightly packed RGBA. Display
+/// affinity does not apply to this, so it shows the real content.
+fn render_window(hwnd: HWND, width: i32, height: i32) -> std::result::Result<Vec<u8>, String> {
+    if width <= 0 || height <= 0 {
+        return Err("Window has no area".to_string());
+    }
+    unsafe {
+        let screen_dc = GetDC(HWND(0));
+        let mem_dc = CreateCompatibleDC(screen_dc);
+        let bitmap = CreateCompatibleBitmap(screen_dc, width, height);
+        let previous = SelectObject(mem_dc, bitmap);
+
+        // Full content also covers DirectComposition and GPU-drawn windows
+        let printed = PrintWindow(hwnd, mem_dc, PRINT_WINDOW_FLAGS(PW_RENDERFULLCONTENT)).as_bool();
+
+        let mut info = BITMAPINFO {
+            bmiHeader: BITMAPINFOHEADER {
+                biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: width,
+                biHeight: -height, // top-down rows
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut bgra = vec![0u8; width as usize * height as usize * 4];
+        let lines = GetDIBits(mem_dc, bitmap, 0, height as u32, Some(bgra.as_mut_ptr() as *mut _), &mut info, DIB_RGB_COLORS);
+
+        SelectObject(mem_dc, previous);
+        let _ = DeleteObject(bitmap);
+        let _ = DeleteDC(mem_dc);
+        ReleaseDC(HWND(0), screen_dc);
+
+        if !printed || lines != height {
+            return Err("Could not render the window".to_string());
+        }
+
+        // 32-bit DIB rows are never padded
+        let layout = frame::FrameLayout {
+            width: width as u32,
+            height: height as u32,
+            row_pitch: width as usize * 4,
+            format: frame::PixelFormat::Bgra8,
+        };
+        frame::to_rgba(&bgra, &layout).map(|rgba| rgba.into_owned())
+    }
+}
+
+/// One frame of `item` from a short-lived capture session, at the highest
+/// preview quality.
+fn capture_one_frame<T: TryIntoCaptureItemWithType + Clone + Send + 'static>(item: T) -> std::result::Result<frame::RgbaFrame, String> {
+    let (sender, receiver) = crossbeam_channel::bounded(1);
+    let control = start_capture(item, CaptureFlags::headless(sender))?;
+    let image = receiver.recv_timeout(Duration::from_secs(3));
+    let _ = control.stop();
+
+    let image = image.map_err(|_| "No frame arrived from Graphics Capture".to_string())?;
+    Ok(rgba_frame(&image))
+}
+
+/// Records a clip of `item` with a dedicated capture session. Blocks for
+/// the length of the clip; used by the CLI `record` command.
+#[allow(dead_code)]
+fn record_clip<T: TryIntoCaptureItemWithType + Clone + Send + 'static>(
+    item: T,
+    settings: recording::ClipSettings,
+) -> std::result::Result<Vec<Arc<frame::RgbaFrame>>, String> {
+    let (sender, receiver) = crossbeam_channel::bounded(1);
+    let control = start_capture(item, CaptureFlags::headless(sender))?;
+    let mut recorder = recording::ClipRecorder::new(settings);
+
+    // The clip clock starts with the first frame
+    let first = receiver.recv_timeout(Duration::from_secs(3));
+    if let Ok(image) = first {
+        recorder.push(Arc::new(rgba_frame(&image)));
+        while !recorder.is_complete() {
+            if let Ok(image) = receiver.recv_timeout(settings.interval()) {
+                recorder.push(Arc::new(rgba_frame(&image)));
+            }
+            recorder.tick(Instant::now());
+        }
+    }
+    let _ = control.stop();
+
+    let frames = recorder.into_frames();
+    if frames.is_empty() {
+        return Err("No frame arrived from Graphics Capture".to_string());
+    }
+    Ok(frames)
+}
+
+fn rgba_frame(image: &egui::ColorImage) -> frame::RgbaFrame {
+    // Preview frames are opaque, so premultiplied and straight alpha agree
+    frame::RgbaFrame {
+        width: image.size[0] as u32,
+        height: image.size[1] as u32,
+        pixels: image.as_raw().to_vec(),
+    }
+}
+
 fn get_eframe_hwnd(frame: &eframe::Frame) -> HWND {
     match frame.window_handle().unwrap().as_raw() {
         RawWindowHandle::Win32(handle) => HWND(handle.hwnd.get() as isize),
@@ -1363,7 +3059,7 @@ fn get_eframe_hwnd(frame: &eframe::Frame) -> HWND {
 // In your win32 module
 pub fn enumerate_windows(ctx: &egui::Context) -> Vec<AppWindow> {
     let mut list = Vec::new();
-    let mut params = (list, ctx.clone());
+    let mut params = (list, ctx.clone(), elevation::current_token());
     
     unsafe extern "system" fn enum_proc(hwnd: HWND, lparam: LPARAM) -> BOOL {
         if !IsWindowVisible(hwnd).as_bool() { return BOOL(1); }
@@ -1380,14 +3076,17 @@ pub fn enumerate_windows(ctx: &egui::Context) -> Vec<AppWindow> {
         GetWindowThreadProcessId(hwnd, Some(&mut pid));
         // --------------------
 
-        let (list, ctx) = &mut *(lparam.0 as *mut (Vec<AppWindow>, egui::Context));
+        let (list, ctx, current) = &mut *(lparam.0 as *mut (Vec<AppWindow>, egui::Context, elevation::TokenInfo));
+        let token = elevation::query_process_token(pid);
         list.push(AppWindow { 
             hwnd, 
             pid, // Store it
             title, 
             is_taskbar_hidden: false, 
             is_capture_hidden: false, 
-            icon_texture: get_window_icon(hwnd, ctx) 
+            icon_texture: get_window_icon(hwnd, ctx),
+            requires_admin: elevation::requires_admin(current, token.as_ref()),
+            token,
         });
         BOOL(1)
     }
@@ -1397,9 +3096,10 @@ pub fn enumerate_windows(ctx: &egui::Context) -> Vec<AppWindow> {
 }
 
 fn get_config_dir() -> PathBuf {
-    let mut config_dir = env::var("LOCALAPPDATA")
+    // var_os: a non-Unicode profile path must not silently fall back to ./config
+    let mut config_dir = env::var_os("LOCALAPPDATA")
         .map(PathBuf::from)
-        .unwrap_or_else(|_| PathBuf::from(".").join("config"));
+        .unwrap_or_else(|| PathBuf::from(".").join("config"));
     config_dir.push(APP_NAME);
     if !config_dir.exists() {
         std::fs::create_dir_all(&config_dir).ok();
@@ -1407,6 +3107,20 @@ fn get_config_dir() -> PathBuf {
     config_dir
 }
 
+/// Where installers downloaded by the update dialog are kept.
+fn get_updates_dir() -> PathBuf {
+    get_config_dir().join("updates")
+}
+
+/// Where snapshots and clips are saved.
+fn get_captures_dir() -> PathBuf {
+    let dir = get_config_dir().join("captures");
+    if !dir.exists() {
+        std::fs::create_dir_all(&dir).ok();
+    }
+    dir
+}
+
 fn load_auto_hide_list() -> Vec<String> {
     let config_dir = get_config_dir();
     let file_path = config_dir.join("autohide.txt");
@@ -1427,28 +3141,184 @@ fn save_auto_hide_list(list: &[String]) -> std::io::Result<()> {
     std::fs::write(file_path, content)
 }
 
+/// Settings from settings.json, migrated to the current schema and checked,
+/// leaving the file as it is.
 fn load_settings() -> AppSettings {
-    let config_dir = get_config_dir();
-    let file_path = config_dir.join("settings.json");
-    match std::fs::read_to_string(file_path) {
-        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|_| AppSettings { 
-            enable_auto_update: true,
-            preview_quality: 2,
-        }),
-        Err(_) => AppSettings { 
-            enable_auto_update: true,
-            preview_quality: 2,
-        },
+    let settings = std::fs::read(get_config_dir().join("settings.json"))
+        .map(|content| schema::parse::<AppSettings>(&String::from_utf8_lossy(&content), SETTINGS_LIMITS).settings)
+        .unwrap_or_default();
+    decrypt_password(settings)
+}
+
+/// Settings for startup. A file with problems, or of another version, is
+/// kept as a backup and replaced by the repaired settings, and the notice
+/// says what was reset.
+fn load_settings_with_notice() -> (AppSettings, Option<String>) {
+    let file_path = get_config_dir().join("settings.json");
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut saved = false;
+    let (settings, notice) = schema::load_and_repair(&file_path, SETTINGS_LIMITS, &stamp, back_up_settings, |settings: &AppSettings| {
+        saved = save_settings(&decrypt_password(settings.clone())).is_ok();
+        saved
+    });
+    let settings = decrypt_password(settings);
+    // An older version saved the proxy password in plain text
+    if !saved && settings.proxy.protected_password.is_empty() && !settings.proxy.password.is_empty() {
+        let _ = save_settings(&settings);
+    }
+    (settings, notice)
+}
+
+/// `settings` as read, with the proxy password decrypted. A plain-text
+/// password from an older version is kept as it is.
+fn decrypt_password(mut settings: AppSettings) -> AppSettings {
+    if settings.proxy.password.is_empty() {
+        settings.proxy.password = unprotect_password(&settings.proxy.protected_password).unwrap_or_default();
     }
+    settings
+}
+
+/// Keeps `content` of settings.json as `backup`, with a plain-text proxy
+/// password encrypted the way `save_settings` stores it.
+fn back_up_settings(content: &[u8], backup: &Path) -> bool {
+    let encrypted = serde_json::from_slice::<serde_json::Value>(content).ok().and_then(|mut fields| {
+        let proxy = fields.get_mut("proxy")?.as_object_mut()?;
+        let password = proxy.remove("password")?;
+        let protected = password.as_str().and_then(protect_password).unwrap_or_default();
+        proxy.insert("protected_password".to_string(), protected.into());
+        serde_json::to_vec_pretty(&fields).ok()
+    });
+    std::fs::write(backup, encrypted.as_deref().unwrap_or(content)).is_ok()
 }
 
 fn save_settings(settings: &AppSettings) -> std::io::Result<()> {
     let config_dir = get_config_dir();
     let file_path = config_dir.join("settings.json");
-    let content = serde_json::to_string_pretty(settings).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    // The password goes to the file encrypted, or not at all
+    let mut settings = settings.clone();
+    settings.proxy.protected_password = protect_password(&settings.proxy.password).unwrap_or_default();
+    let content = serde_json::to_string_pretty(&settings).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    std::fs::write(file_path, content)
+}
+
+/// `password` encrypted with DPAPI so that only the current Windows user can
+/// read it, in Base64.
+fn protect_password(password: &str) -> Option<String> {
+    if password.is_empty() {
+        return Some(String::new());
+    }
+    let input = CRYPT_INTEGER_BLOB { cbData: password.len() as u32, pbData: password.as_ptr() as *mut u8 };
+    let mut output = CRYPT_INTEGER_BLOB::default();
+    unsafe { CryptProtectData(&input, w!("WinHider proxy password"), None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output) }.ok()?;
+    let protected = unsafe { std::slice::from_raw_parts(output.pbData, output.cbData as usize) }.to_vec();
+    unsafe { let _ = LocalFree(HLOCAL(output.pbData.cast())); }
+    Some(proxy::base64(&protected))
+}
+
+/// Decrypts a password from `protect_password`. `None` when it was encrypted
+/// by another Windows user or on another machine.
+fn unprotect_password(protected: &str) -> Option<String> {
+    if protected.is_empty() {
+        return Some(String::new());
+    }
+    let mut data = proxy::base64_decode(protected)?;
+    let input = CRYPT_INTEGER_BLOB { cbData: data.len() as u32, pbData: data.as_mut_ptr() };
+    let mut output = CRYPT_INTEGER_BLOB::default();
+    unsafe { CryptUnprotectData(&input, None, None, None, None, CRYPTPROTECT_UI_FORBIDDEN, &mut output) }.ok()?;
+    let password = unsafe { std::slice::from_raw_parts(output.pbData, output.cbData as usize) }.to_vec();
+    unsafe { let _ = LocalFree(HLOCAL(output.pbData.cast())); }
+    String::from_utf8(password).ok()
+}
+
+/// HTTP client for update checks and downloads, going through the proxy
+/// `settings` select.
+fn http_client(settings: &proxy::ProxySettings) -> Result<update::UreqClient, String> {
+    let proxy = proxy::resolve(settings, |name| env::var(name).ok(), read_system_proxy)?;
+    update::UreqClient::with_proxy(USER_AGENT, proxy)
+}
+
+/// The releases published at `source`, answered from the update cache when
+/// a recent check allows it, unless `forced`.
+fn fetch_releases(settings: &proxy::ProxySettings, source: &update::Source, forced: bool) -> Result<Vec<update::Release>, String> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+    let mut cache = load_update_cache();
+    let releases = http_client(settings).and_then(|client| update::fetch_releases(&client, source, &mut cache, now, forced));
+    let _ = save_update_cache(&cache);
+    releases
+}
+
+/// The current user's proxy from Settings → Network & Internet → Proxy,
+/// which Windows keeps in the registry.
+fn read_system_proxy() -> Option<proxy::SystemProxy> {
+    let key = w!("Software\\Microsoft\\Windows\\CurrentVersion\\Internet Settings");
+    let string = |name: PCWSTR| -> Option<String> {
+        let mut size = 0u32;
+        unsafe { RegGetValueW(HKEY_CURRENT_USER, key, name, RRF_RT_REG_SZ, None, None, Some(&mut size)) }.ok()?;
+        let mut buffer = vec![0u16; (size as usize).div_ceil(2)];
+        unsafe { RegGetValueW(HKEY_CURRENT_USER, key, name, RRF_RT_REG_SZ, None, Some(buffer.as_mut_ptr().cast()), Some(&mut size)) }.ok()?;
+        let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+        Some(String::from_utf16_lossy(&buffer[..len]))
+    };
+
+    let mut enabled = 0u32;
+    let mut size = std::mem::size_of::<u32>() as u32;
+    unsafe {
+        RegGetValueW(HKEY_CURRENT_USER, key, w!("ProxyEnable"), RRF_RT_REG_DWORD, None, Some((&mut enabled as *mut u32).cast()), Some(&mut size))
+    }.ok()?;
+    Some(proxy::SystemProxy {
+        enabled: enabled != 0,
+        server: string(w!("ProxyServer")).unwrap_or_default(),
+        overrides: string(w!("ProxyOverride")).unwrap_or_default(),
+    })
+}
+
+/// Starts a downloaded installer once its SHA-256 still matches. The shell
+/// asks for elevation if the installer needs it.
+fn launch_installer(installer: &download::Installer) -> Result<(), String> {
+    installer.recheck()?;
+    let path = paths::to_wide_nul(&installer.path);
+    let result = unsafe { ShellExecuteW(HWND(0), w!("open"), PCWSTR(path.as_ptr()), PCWSTR::null(), PCWSTR::null(), SW_SHOWNORMAL) };
+    // ShellExecuteW returns a value greater than 32 on success
+    if result.0 <= 32 {
+        return Err(format!("Failed to run the installer (code {})", result.0));
+    }
+    Ok(())
+}
+
+// ETag, last response and rate-limit wait of the update checker.
+fn load_update_cache() -> update::Cache {
+    let config_dir = get_config_dir();
+    let file_path = config_dir.join("update_cache.json");
+    std::fs::read_to_string(file_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_update_cache(cache: &update::Cache) -> std::io::Result<()> {
+    let config_dir = get_config_dir();
+    let file_path = config_dir.join("update_cache.json");
+    let content = serde_json::to_string_pretty(cache).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    std::fs::write(file_path, content)
+}
+
+// Session handoff for "Relaunch as Administrator": written by the old instance,
+// consumed (and deleted) by the elevated one.
+fn save_session(session: &SessionState) -> std::io::Result<()> {
+    let config_dir = get_config_dir();
+    let file_path = config_dir.join("session.json");
+    let content = serde_json::to_string_pretty(session).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
     std::fs::write(file_path, content)
 }
 
+fn take_session() -> Option<SessionState> {
+    let config_dir = get_config_dir();
+    let file_path = config_dir.join("session.json");
+    let content = std::fs::read_to_string(&file_path).ok()?;
+    let _ = std::fs::remove_file(&file_path);
+    serde_json::from_str(&content).ok()
+}
+
 impl WinHiderApp {
     fn should_auto_hide(&self, window_title: &str) -> bool {
         for app_name in &self.auto_hide_list {
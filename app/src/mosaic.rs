@@ -0,0 +1,143 @@
+/*
+ * =============================================================================
+ * WinHider Mosaic - All-Monitor Preview Layout
+ * =============================================================================
+ *
+ * Filename: mosaic.rs
+ * Description: Lays every monitor out in one preview pane the way they are
+ *              arranged on the virtual desktop, and splits the preview frame
+ *              budget between their capture sessions.
+ *
+ * Notes:
+ * - The whole desktop is letterboxed into the pane like a single frame, so
+ *   monitors of different sizes keep their relative scale, offsets and gaps.
+ *   Negative coordinates (monitors left of or above the primary) work the
+ *   same as any other.
+ * - Every monitor runs its own capture session. The frame budget is shared,
+ *   so three monitors at 30 fps cost about as much as one.
+ * =============================================================================
+ */
+
+use crate::letterbox::{self, FitRect};
+use crate::overlay;
+use crate::pacing;
+use crate::verify::ScreenRect;
+
+/// Placement of the whole desktop and of each monitor in the pane.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Layout {
+    /// Bounding box of all monitors, in screen pixels.
+    pub desktop: ScreenRect,
+    /// Where `desktop` is drawn, in pane points.
+    pub image: FitRect,
+    /// One tile per monitor, in the order given.
+    pub tiles: Vec<FitRect>,
+}
+
+/// Smallest rectangle containing every monitor.
+pub fn bounds(monitors: &[ScreenRect]) -> Option<ScreenRect> {
+    monitors.iter().copied().reduce(|a, b| ScreenRect {
+        left: a.left.min(b.left),
+        top: a.top.min(b.top),
+        right: a.right.max(b.right),
+        bottom: a.bottom.max(b.bottom),
+    })
+}
+
+/// Lays `monitors` out in a `pane_w` x `pane_h` point pane. `None` when
+/// there are no monitors or the desktop has no area.
+pub fn layout(monitors: &[ScreenRect], pane_w: f32, pane_h: f32, pixels_per_point: f32) -> Option<Layout> {
+    let desktop = bounds(monitors).filter(|d| !d.is_empty())?;
+    let image = letterbox::letterbox(desktop.width() as u32, desktop.height() as u32, pane_w, pane_h, pixels_per_point);
+    let tiles = monitors.iter()
+        .map(|m| overlay::map_rect(*m, desktop, image).unwrap_or_default())
+        .collect();
+    Some(Layout { desktop, image, tiles })
+}
+
+/// Frame rate for each of `monitors` sessions so that together they stay
+/// within `total_fps`. Every monitor still gets at least the minimum rate.
+pub fn per_monitor_fps(total_fps: u32, monitors: usize) -> u32 {
+    let monitors = monitors.clamp(1, u32::MAX as usize) as u32;
+    (total_fps / monitors).max(pacing::MIN_FPS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::overlay::tests::{fit, screen};
+
+    #[test]
+    fn single_monitor_matches_the_plain_preview() {
+        let monitor = screen(0, 0, 1920, 1080);
+        let got = layout(&[monitor], 500.0, 300.0, 1.0).unwrap();
+        let plain = letterbox::letterbox(1920, 1080, 500.0, 300.0, 1.0);
+        assert_eq!(got.image, plain);
+        let tile = got.tiles[0];
+        for (a, b) in [(tile.x, plain.x), (tile.y, plain.y), (tile.width, plain.width), (tile.height, plain.height)] {
+            assert!((a - b).abs() < 1e-3, "{:?} vs {:?}", tile, plain);
+        }
+    }
+
+    #[test]
+    fn side_by_side_monitors_share_the_width() {
+        let monitors = [screen(0, 0, 1920, 1080), screen(1920, 0, 3840, 1080)];
+        let got = layout(&monitors, 640.0, 180.0, 1.0).unwrap();
+        assert_eq!(got.desktop, screen(0, 0, 3840, 1080));
+        assert_eq!(got.tiles, vec![fit(0.0, 0.0, 320.0, 180.0), fit(320.0, 0.0, 320.0, 180.0)]);
+    }
+
+    #[test]
+    fn stacked_monitors_share_the_height() {
+        let monitors = [screen(0, -1080, 1920, 0), screen(0, 0, 1920, 1080)];
+        let got = layout(&monitors, 160.0, 180.0, 1.0).unwrap();
+        assert_eq!(got.tiles, vec![fit(0.0, 0.0, 160.0, 90.0), fit(0.0, 90.0, 160.0, 90.0)]);
+    }
+
+    #[test]
+    fn mixed_sizes_keep_relative_scale_and_offsets() {
+        // A 1440p monitor left of the primary, bottom-aligned with it
+        let monitors = [screen(0, 0, 1920, 1080), screen(-2560, -360, 0, 1080)];
+        let got = layout(&monitors, 448.0, 144.0, 1.0).unwrap();
+        assert_eq!(got.desktop, screen(-2560, -360, 1920, 1080));
+        assert_eq!(got.image, fit(0.0, 0.0, 448.0, 144.0));
+        assert_eq!(got.tiles[0], fit(256.0, 36.0, 192.0, 108.0));
+        assert_eq!(got.tiles[1], fit(0.0, 0.0, 256.0, 144.0));
+    }
+
+    #[test]
+    fn tiles_never_overlap_or_leave_the_image() {
+        let monitors = [
+            screen(0, 0, 2560, 1440),
+            screen(2560, 200, 3640, 2120),
+            screen(-1920, 300, 0, 1380),
+        ];
+        let got = layout(&monitors, 600.0, 400.0, 1.25).unwrap();
+        let image = got.image;
+        for (i, a) in got.tiles.iter().enumerate() {
+            assert!(a.x >= image.x - 1e-3 && a.y >= image.y - 1e-3);
+            assert!(a.x + a.width <= image.x + image.width + 1e-3);
+            assert!(a.y + a.height <= image.y + image.height + 1e-3);
+            for b in &got.tiles[i + 1..] {
+                let overlap_w = (a.x + a.width).min(b.x + b.width) - a.x.max(b.x);
+                let overlap_h = (a.y + a.height).min(b.y + b.height) - a.y.max(b.y);
+                assert!(overlap_w <= 1e-3 || overlap_h <= 1e-3, "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn nothing_to_lay_out() {
+        assert_eq!(layout(&[], 500.0, 300.0, 1.0), None);
+        assert_eq!(layout(&[screen(0, 0, 0, 0)], 500.0, 300.0, 1.0), None);
+        assert_eq!(bounds(&[]), None);
+    }
+
+    #[test]
+    fn budget_is_split_between_monitors() {
+        assert_eq!(per_monitor_fps(30, 1), 30);
+        assert_eq!(per_monitor_fps(30, 3), 10);
+        assert_eq!(per_monitor_fps(5, 4), 1);
+        assert_eq!(per_monitor_fps(30, 0), 30);
+    }
+}
@@ -0,0 +1,340 @@
+/*
+ * =============================================================================
+ * WinHider Recording - Preview Snapshots & Clips
+ * =============================================================================
+ *
+ * Filename: recording.rs
+ * Description: Saves what a capture actually contained: single preview frames
+ *              as PNG and short clips of the preview stream as animated GIF
+ *              or APNG. Used by the GUI preview and the `snapshot`/`record`
+ *              CLI commands.
+ *
+ * Notes:
+ * - Graphics Capture only delivers a frame when something on screen changes,
+ *   so the recorder samples the newest frame on a fixed clock instead of
+ *   keeping every frame it is given. A still screen still yields a clip of
+ *   the requested length.
+ * - Repeated frames are written once with a longer delay.
+ * - Frames that change size mid-clip (a resized or switched source) are
+ *   skipped, as both formats need one canvas size.
+ * - GIF encoding goes through `image`; APNG through `png`, which `image`
+ *   already uses for still PNGs.
+ * =============================================================================
+ */
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::codecs::png::PngEncoder;
+use image::{ColorType, Delay, ImageEncoder, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::frame::RgbaFrame;
+
+pub const MAX_FPS: u32 = 30;
+pub const MAX_DURATION_SECS: u32 = 30;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipFormat {
+    #[default]
+    Gif,
+    Apng,
+}
+
+impl ClipFormat {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Gif => "GIF",
+            Self::Apng => "APNG",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::Apng => "png",
+        }
+    }
+
+    /// Format implied by a file name: `.gif`, or `.png`/`.apng`. Used by
+    /// the CLI `record` command.
+    #[allow(dead_code)]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "gif" => Some(Self::Gif),
+            "png" | "apng" => Some(Self::Apng),
+            _ => None,
+        }
+    }
+}
+
+/// Frame rate and length of a clip, clamped to sane limits.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ClipSettings {
+    pub fps: u32,
+    pub duration_secs: u32,
+}
+
+impl ClipSettings {
+    pub fn new(fps: u32, duration_secs: u32) -> Self {
+        Self {
+            fps: fps.clamp(1, MAX_FPS),
+            duration_secs: duration_secs.clamp(1, MAX_DURATION_SECS),
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        (self.fps * self.duration_secs) as usize
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(1) / self.fps
+    }
+}
+
+/// Samples a stream of frames at a fixed rate until the clip is full.
+pub struct ClipRecorder {
+    settings: ClipSettings,
+    latest: Option<Arc<RgbaFrame>>,
+    frames: Vec<Arc<RgbaFrame>>,
+    next_due: Option<Instant>,
+}
+
+impl ClipRecorder {
+    pub fn new(settings: ClipSettings) -> Self {
+        Self { settings, latest: None, frames: Vec::new(), next_due: None }
+    }
+
+    pub fn settings(&self) -> ClipSettings {
+        self.settings
+    }
+
+    /// Newest frame from the stream. Ignored if its size differs from the
+    /// frames already recorded.
+    pub fn push(&mut self, frame: Arc<RgbaFrame>) {
+        let canvas = self.frames.first().or(self.latest.as_ref());
+        if canvas.is_some_and(|c| (c.width, c.height) != (frame.width, frame.height)) {
+            return;
+        }
+        self.latest = Some(frame);
+    }
+
+    /// Records the newest frame once for every sample time up to `now`.
+    /// The clock starts with the first tick that has a frame.
+    pub fn tick(&mut self, now: Instant) {
+        let Some(latest) = &self.latest else {
+            return;
+        };
+        let mut due = self.next_due.unwrap_or(now);
+        while due <= now && self.frames.len() < self.settings.frame_count() {
+            self.frames.push(latest.clone());
+            due += self.settings.interval();
+        }
+        self.next_due = Some(due);
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.frames.len() >= self.settings.frame_count()
+    }
+
+    /// (recorded, total) frames.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.frames.len(), self.settings.frame_count())
+    }
+
+    pub fn into_frames(self) -> Vec<Arc<RgbaFrame>> {
+        self.frames
+    }
+}
+
+/// Consecutive repeats of the same frame, as (frame, count).
+fn runs(frames: &[Arc<RgbaFrame>]) -> Vec<(&RgbaFrame, u32)> {
+    let mut out: Vec<(&RgbaFrame, u32)> = Vec::new();
+    for frame in frames {
+        match out.last_mut() {
+            Some((last, count)) if std::ptr::eq(*last, frame.as_ref()) || **last == **frame => *count += 1,
+            _ => out.push((frame.as_ref(), 1)),
+        }
+    }
+    out
+}
+
+pub fn encode_png<W: Write>(writer: W, frame: &RgbaFrame) -> Result<(), String> {
+    PngEncoder::new(writer)
+        .write_image(&frame.pixels, frame.width, frame.height, ColorType::Rgba8)
+        .map_err(|e| format!("Failed to encode PNG: {}", e))
+}
+
+pub fn encode_clip<W: Write>(writer: W, format: ClipFormat, frames: &[Arc<RgbaFrame>], fps: u32) -> Result<(), String> {
+    let Some(first) = frames.first() else {
+        return Err("No frames were recorded".to_string());
+    };
+    let fps = fps.clamp(1, MAX_FPS);
+    let runs = runs(frames);
+
+    match format {
+        ClipFormat::Gif => {
+            let mut encoder = GifEncoder::new_with_speed(writer, 10);
+            encoder.set_repeat(Repeat::Infinite).map_err(|e| e.to_string())?;
+            for (frame, count) in runs {
+                let image = RgbaImage::from_raw(frame.width, frame.height, frame.pixels.clone())
+                    .ok_or("Frame buffer does not match its size")?;
+                let delay = Delay::from_numer_denom_ms(1000 * count, fps);
+                encoder.encode_frame(image::Frame::from_parts(image, 0, 0, delay))
+                    .map_err(|e| format!("Failed to encode GIF: {}", e))?;
+            }
+            Ok(())
+        }
+        ClipFormat::Apng => {
+            let map_err = |e: png::EncodingError| format!("Failed to encode APNG: {}", e);
+            let mut encoder = png::Encoder::new(writer, first.width, first.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_animated(runs.len() as u32, 0).map_err(map_err)?;
+            let mut writer = encoder.write_header().map_err(map_err)?;
+            for (frame, count) in runs {
+                let delay = u16::try_from(count).unwrap_or(u16::MAX);
+                writer.set_frame_delay(delay, fps as u16).map_err(map_err)?;
+                writer.write_image_data(&frame.pixels).map_err(map_err)?;
+            }
+            writer.finish().map_err(map_err)
+        }
+    }
+}
+
+pub fn save_snapshot(path: &Path, frame: &RgbaFrame) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    encode_png(BufWriter::new(file), frame)
+}
+
+pub fn save_clip(path: &Path, format: ClipFormat, frames: &[Arc<RgbaFrame>], fps: u32) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    encode_clip(BufWriter::new(file), format, frames, fps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::AnimationDecoder;
+
+    fn solid(width: u32, height: u32, value: u8) -> Arc<RgbaFrame> {
+        Arc::new(RgbaFrame { width, height, pixels: [value, value / 2, 255 - value, 255].repeat((width * height) as usize) })
+    }
+
+    #[test]
+    fn settings_are_clamped() {
+        assert_eq!(ClipSettings::new(0, 0), ClipSettings { fps: 1, duration_secs: 1 });
+        assert_eq!(ClipSettings::new(120, 600), ClipSettings { fps: MAX_FPS, duration_secs: MAX_DURATION_SECS });
+        assert_eq!(ClipSettings::new(10, 3).frame_count(), 30);
+        assert_eq!(ClipSettings::new(4, 1).interval(), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(ClipFormat::from_path(Path::new("clip.GIF")), Some(ClipFormat::Gif));
+        assert_eq!(ClipFormat::from_path(Path::new("clip.png")), Some(ClipFormat::Apng));
+        assert_eq!(ClipFormat::from_path(Path::new("clip.apng")), Some(ClipFormat::Apng));
+        assert_eq!(ClipFormat::from_path(Path::new("clip.mp4")), None);
+        assert_eq!(ClipFormat::from_path(Path::new("clip")), None);
+    }
+
+    #[test]
+    fn recorder_samples_on_a_fixed_clock() {
+        let start = Instant::now();
+        let mut rec = ClipRecorder::new(ClipSettings::new(10, 1));
+
+        // Nothing to record before the first frame
+        rec.tick(start);
+        assert_eq!(rec.progress(), (0, 10));
+
+        rec.push(solid(4, 4, 1));
+        rec.tick(start);
+        assert_eq!(rec.progress().0, 1);
+
+        // A burst of frames inside one interval only counts once
+        for v in 2..6 {
+            rec.push(solid(4, 4, v));
+            rec.tick(start + Duration::from_millis(50));
+        }
+        assert_eq!(rec.progress().0, 1);
+
+        // A still screen keeps repeating the last frame
+        rec.tick(start + Duration::from_millis(450));
+        assert_eq!(rec.progress().0, 5);
+        assert!(!rec.is_complete());
+
+        rec.tick(start + Duration::from_secs(5));
+        assert!(rec.is_complete());
+        assert_eq!(rec.into_frames().len(), 10);
+    }
+
+    #[test]
+    fn recorder_skips_frames_of_another_size() {
+        let start = Instant::now();
+        let mut rec = ClipRecorder::new(ClipSettings::new(1, 3));
+        rec.push(solid(4, 4, 1));
+        rec.tick(start);
+        rec.push(solid(8, 8, 2));
+        rec.tick(start + Duration::from_secs(1));
+        let frames = rec.into_frames();
+        assert!(frames.iter().all(|f| f.width == 4));
+    }
+
+    #[test]
+    fn repeated_frames_collapse_into_runs() {
+        let a = solid(2, 2, 10);
+        let b = solid(2, 2, 20);
+        let frames = vec![a.clone(), a.clone(), b.clone(), solid(2, 2, 20), a];
+        let counts: Vec<u32> = runs(&frames).iter().map(|(_, n)| *n).collect();
+        assert_eq!(counts, vec![2, 2, 1]);
+    }
+
+    #[test]
+    fn png_snapshot_round_trips() {
+        let frame = solid(5, 3, 40);
+        let mut out = Vec::new();
+        encode_png(&mut out, &frame).unwrap();
+        let decoded = image::load_from_memory(&out).unwrap().to_rgba8();
+        assert_eq!((decoded.width(), decoded.height()), (5, 3));
+        assert_eq!(decoded.as_raw(), &frame.pixels);
+    }
+
+    #[test]
+    fn gif_clip_has_one_frame_per_run() {
+        let a = solid(6, 4, 0);
+        let frames = vec![a.clone(), a, solid(6, 4, 200)];
+        let mut out = Vec::new();
+        encode_clip(&mut out, ClipFormat::Gif, &frames, 10).unwrap();
+
+        let decoder = image::codecs::gif::GifDecoder::new(std::io::Cursor::new(out)).unwrap();
+        let decoded: Vec<image::Frame> = decoder.into_frames().collect::<Result<_, _>>().unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].delay().numer_denom_ms(), (200, 1));
+        assert_eq!(decoded[0].buffer().dimensions(), (6, 4));
+    }
+
+    #[test]
+    fn apng_clip_is_animated() {
+        let frames = vec![solid(3, 3, 0), solid(3, 3, 90), solid(3, 3, 180)];
+        let mut out = Vec::new();
+        encode_clip(&mut out, ClipFormat::Apng, &frames, 5).unwrap();
+
+        let reader = png::Decoder::new(std::io::Cursor::new(out)).read_info().unwrap();
+        let info = reader.info();
+        assert_eq!((info.width, info.height), (3, 3));
+        assert_eq!(info.animation_control.unwrap().num_frames, 3);
+        let fc = info.frame_control.unwrap();
+        assert_eq!((fc.delay_num, fc.delay_den), (1, 5));
+    }
+
+    #[test]
+    fn empty_clip_is_an_error() {
+        assert!(encode_clip(Vec::new(), ClipFormat::Gif, &[], 10).is_err());
+    }
+}
@@ -0,0 +1,353 @@
+/*
+ * =============================================================================
+ * WinHider Schema - Versioned Settings File
+ * =============================================================================
+ *
+ * Filename: schema.rs
+ * Description: Reads settings.json through its schema version: migrates files
+ *              written by older versions, checks every field, and keeps the
+ *              good settings of a file with a bad one.
+ *
+ * Notes:
+ * - The file records `schema_version`. Files without one predate it and are
+ *   version 0. `MIGRATIONS[n]` turns a version `n` file into version `n + 1`,
+ *   so an old file passes through every step up to `SCHEMA_VERSION` in turn.
+ * - A field of the wrong type, or a number outside its `Limit`, is reset to
+ *   its default on its own and named in a `Problem`; the other fields keep
+ *   their values. Only a file that is not JSON at all resets everything.
+ * - A file from a newer WinHider is read as far as it is understood, and
+ *   reported, since saving it again drops what this version does not know.
+ * - At startup, a file with problems or from another version is kept as a
+ *   backup and then saved in this version's schema, so the next start finds
+ *   nothing to report. Without a backup the file is left alone.
+ * =============================================================================
+ */
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+pub const VERSION_FIELD: &str = "schema_version";
+
+/// Upgrades the fields of a file by one version.
+pub type Migration = fn(&mut Map<String, Value>);
+
+/// Step `n` upgrades version `n` to `n + 1`.
+pub const MIGRATIONS: &[Migration] = &[unversioned];
+
+/// Version of the settings file this build writes, the one the last
+/// migration leads to.
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Version 0 to 1: files written before the schema was versioned. Every
+/// field they can hold means the same in version 1.
+fn unversioned(_: &mut Map<String, Value>) {}
+
+/// Inclusive range of a numeric field.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Limit {
+    pub field: &'static str,
+    pub min: u64,
+    pub max: u64,
+}
+
+/// Something wrong with the file, for the notice shown to the user.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Problem {
+    /// Not a JSON object; every setting is back to its default.
+    Unreadable(String),
+    /// `field` was invalid and is back to its default.
+    Field { field: String, reason: String },
+    /// Written by a newer WinHider with this schema version.
+    Newer(u64),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Unreadable(reason) => write!(f, "The file could not be read ({}), so every setting was reset", reason),
+            Problem::Field { field, reason } => write!(f, "\"{}\" {}, so it was reset", field, reason),
+            Problem::Newer(version) => write!(
+                f,
+                "The file is from a newer WinHider (settings version {}); settings this version does not know were dropped",
+                version
+            ),
+        }
+    }
+}
+
+/// Settings read from a file, with what had to be fixed on the way.
+#[derive(Debug)]
+pub struct Loaded<T> {
+    pub settings: T,
+    /// Schema version the file had.
+    pub version: u64,
+    pub problems: Vec<Problem>,
+}
+
+/// Reads settings from `text`, migrating and checking them against `limits`.
+pub fn parse<T: DeserializeOwned + Default>(text: &str, limits: &[Limit]) -> Loaded<T> {
+    parse_with(text, MIGRATIONS, limits)
+}
+
+fn parse_with<T: DeserializeOwned + Default>(text: &str, migrations: &[Migration], limits: &[Limit]) -> Loaded<T> {
+    let current = migrations.len() as u64;
+    let unreadable = |reason: String| Loaded { settings: T::default(), version: current, problems: vec![Problem::Unreadable(reason)] };
+    let mut fields = match serde_json::from_str::<Value>(text) {
+        Ok(Value::Object(fields)) => fields,
+        Ok(_) => return unreadable("it does not hold a JSON object".to_string()),
+        Err(e) => return unreadable(e.to_string()),
+    };
+
+    let mut problems = Vec::new();
+    let version = match fields.get(VERSION_FIELD) {
+        None => 0,
+        Some(value) => value.as_u64().unwrap_or_else(|| {
+            problems.push(Problem::Field { field: VERSION_FIELD.to_string(), reason: format!("is not a version number: {}", value) });
+            current
+        }),
+    };
+    if version > current {
+        problems.push(Problem::Newer(version));
+    }
+    for migrate in migrations.iter().skip(version as usize) {
+        migrate(&mut fields);
+    }
+    fields.insert(VERSION_FIELD.to_string(), Value::from(current));
+
+    for limit in limits {
+        let Some(n) = fields.get(limit.field).and_then(Value::as_u64) else {
+            continue;
+        };
+        if n < limit.min || n > limit.max {
+            problems.push(Problem::Field {
+                field: limit.field.to_string(),
+                reason: format!("must be between {} and {}, not {}", limit.min, limit.max, n),
+            });
+            fields.remove(limit.field);
+        }
+    }
+
+    // Each field alone, so that one bad value costs only itself
+    let names: Vec<String> = fields.keys().cloned().collect();
+    for name in names {
+        let alone = Map::from_iter([(name.clone(), fields[&name].clone())]);
+        if let Err(e) = serde_json::from_value::<T>(Value::Object(alone)) {
+            problems.push(Problem::Field { field: name.clone(), reason: format!("is invalid: {}", e) });
+            fields.remove(&name);
+        }
+    }
+
+    match serde_json::from_value(Value::Object(fields)) {
+        Ok(settings) => Loaded { settings, version, problems },
+        Err(e) => unreadable(e.to_string()),
+    }
+}
+
+/// Reads the settings file at `path` at startup. A file with problems or of
+/// an older version is kept through `keep` under a name next to it, then
+/// replaced with the settings read through `save`. `stamp` makes the name of
+/// a backup with problems unique. Returns the settings and, for a file with
+/// problems, the notice.
+pub fn load_and_repair<T: DeserializeOwned + Default>(
+    path: &Path,
+    limits: &[Limit],
+    stamp: &str,
+    mut keep: impl FnMut(&[u8], &Path) -> bool,
+    mut save: impl FnMut(&T) -> bool,
+) -> (T, Option<String>) {
+    let Ok(content) = fs::read(path) else {
+        return (T::default(), None);
+    };
+    let loaded = parse::<T>(&String::from_utf8_lossy(&content), limits);
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    if loaded.problems.is_empty() {
+        // Kept for older versions of WinHider
+        if loaded.version < SCHEMA_VERSION as u64 && keep(&content, &path.with_file_name(format!("{}.v{}.bak", name, loaded.version))) {
+            save(&loaded.settings);
+        }
+        return (loaded.settings, None);
+    }
+
+    let backup = format!("{}.{}.bak", name, stamp);
+    let kept = keep(&content, &path.with_file_name(&backup));
+    if kept {
+        save(&loaded.settings);
+    }
+    let notice = notice(&loaded.problems, kept.then_some(backup.as_str()));
+    (loaded.settings, Some(notice))
+}
+
+/// Notice for the user about `problems`, naming where the original file was
+/// kept. Without a `backup` the file was left alone.
+pub fn notice(problems: &[Problem], backup: Option<&str>) -> String {
+    let mut text = String::from("Some settings could not be loaded:\n");
+    for problem in problems {
+        text.push_str(&format!("• {}.\n", problem));
+    }
+    match backup {
+        Some(backup) => text.push_str(&format!("The original file was kept as {}.", backup)),
+        None => text.push_str("The file could not be backed up, so it was left as it is."),
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    #[serde(default)]
+    struct Settings {
+        schema_version: u64,
+        enabled: bool,
+        fps: u32,
+        name: String,
+        renamed: String,
+    }
+
+    impl Default for Settings {
+        fn default() -> Self {
+            Settings { schema_version: 0, enabled: true, fps: 10, name: "default".to_string(), renamed: String::new() }
+        }
+    }
+
+    const LIMITS: &[Limit] = &[Limit { field: "fps", min: 1, max: 30 }];
+
+    fn field(name: &str, reason: &str) -> Problem {
+        Problem::Field { field: name.to_string(), reason: reason.to_string() }
+    }
+
+    #[test]
+    fn reads_a_current_file() {
+        let loaded: Loaded<Settings> = parse(r#"{"schema_version": 1, "enabled": false, "fps": 30, "name": "x"}"#, LIMITS);
+        assert_eq!(loaded.problems, vec![]);
+        assert_eq!(loaded.version, 1);
+        assert_eq!(loaded.settings, Settings { schema_version: 1, enabled: false, fps: 30, name: "x".to_string(), renamed: String::new() });
+    }
+
+    #[test]
+    fn unversioned_files_are_version_0() {
+        let loaded: Loaded<Settings> = parse(r#"{"enabled": false}"#, LIMITS);
+        assert_eq!((loaded.version, loaded.settings.schema_version), (0, SCHEMA_VERSION as u64));
+        assert!(!loaded.settings.enabled);
+        assert_eq!(loaded.problems, vec![]);
+    }
+
+    #[test]
+    fn migrations_run_in_order_from_the_file_version() {
+        fn rename(fields: &mut Map<String, Value>) {
+            if let Some(old) = fields.remove("old_name") {
+                fields.insert("renamed".to_string(), old);
+            }
+        }
+        fn double(fields: &mut Map<String, Value>) {
+            if let Some(fps) = fields.get("fps").and_then(Value::as_u64) {
+                fields.insert("fps".to_string(), Value::from(fps * 2));
+            }
+        }
+        let migrations: &[Migration] = &[unversioned, rename, double];
+
+        let from_0: Loaded<Settings> = parse_with(r#"{"old_name": "kept", "fps": 5}"#, migrations, LIMITS);
+        assert_eq!((from_0.settings.renamed.as_str(), from_0.settings.fps, from_0.settings.schema_version), ("kept", 10, 3));
+
+        // A version 2 file has been renamed already and is only doubled
+        let from_2: Loaded<Settings> = parse_with(r#"{"schema_version": 2, "old_name": "ignored", "fps": 5}"#, migrations, LIMITS);
+        assert_eq!((from_2.settings.renamed.as_str(), from_2.settings.fps), ("", 10));
+
+        // Limits apply after migrating
+        let too_fast: Loaded<Settings> = parse_with(r#"{"schema_version": 2, "fps": 20}"#, migrations, LIMITS);
+        assert_eq!(too_fast.settings.fps, 10);
+        assert_eq!(too_fast.problems, vec![field("fps", "must be between 1 and 30, not 40")]);
+    }
+
+    #[test]
+    fn a_bad_field_resets_only_itself() {
+        let loaded: Loaded<Settings> = parse(r#"{"schema_version": 1, "enabled": false, "fps": "fast", "name": "kept"}"#, LIMITS);
+        assert_eq!(loaded.settings, Settings { schema_version: 1, enabled: false, fps: 10, name: "kept".to_string(), renamed: String::new() });
+        assert_eq!(loaded.problems.len(), 1);
+        let Problem::Field { field, reason } = &loaded.problems[0] else { panic!("{:?}", loaded.problems) };
+        assert_eq!(field, "fps");
+        assert!(reason.contains("expected u32"), "{}", reason);
+    }
+
+    #[test]
+    fn out_of_range_numbers_are_reset() {
+        let loaded: Loaded<Settings> = parse(r#"{"fps": 0, "enabled": false}"#, LIMITS);
+        assert_eq!((loaded.settings.fps, loaded.settings.enabled), (10, false));
+        assert_eq!(loaded.problems, vec![field("fps", "must be between 1 and 30, not 0")]);
+        assert_eq!(loaded.problems[0].to_string(), "\"fps\" must be between 1 and 30, not 0, so it was reset");
+    }
+
+    #[test]
+    fn unreadable_files_reset_everything() {
+        for text in ["", "{\"enabled\": false,", "[1, 2]", "null", "{\"fps\": 10} trailing"] {
+            let loaded: Loaded<Settings> = parse(text, LIMITS);
+            assert_eq!(loaded.settings, Settings::default(), "{:?}", text);
+            assert!(matches!(loaded.problems[..], [Problem::Unreadable(_)]), "{:?}: {:?}", text, loaded.problems);
+        }
+        let loaded: Loaded<Settings> = parse("{\n  \"enabled\": false,\n  \"fps\": 12\n  \"name\": \"x\"\n}", LIMITS);
+        let Problem::Unreadable(reason) = &loaded.problems[0] else { unreachable!() };
+        assert!(reason.contains("line 4"), "{}", reason);
+    }
+
+    #[test]
+    fn newer_files_are_read_and_reported() {
+        let loaded: Loaded<Settings> = parse(r#"{"schema_version": 7, "enabled": false, "future": true}"#, LIMITS);
+        assert!(!loaded.settings.enabled);
+        assert_eq!(loaded.settings.schema_version, SCHEMA_VERSION as u64);
+        assert_eq!(loaded.problems, vec![Problem::Newer(7)]);
+    }
+
+    #[test]
+    fn a_newer_file_is_kept_and_reported_once() {
+        let dir = std::env::temp_dir().join(format!("winhider-schema-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.json");
+        let newer = r#"{"schema_version": 7, "enabled": false, "future": true}"#;
+        fs::write(&path, newer).unwrap();
+        let keep = |content: &[u8], backup: &Path| fs::write(backup, content).is_ok();
+        let save = |settings: &Settings| fs::write(&path, serde_json::to_string(settings).unwrap()).is_ok();
+
+        let (first, notice) = load_and_repair::<Settings>(&path, LIMITS, "20261018-120000", keep, save);
+        assert!(!first.enabled);
+        let notice = notice.unwrap();
+        assert!(notice.contains("settings version 7") && notice.ends_with("kept as settings.json.20261018-120000.bak."), "{}", notice);
+
+        let (second, notice) = load_and_repair::<Settings>(&path, LIMITS, "20261018-120005", keep, save);
+        assert_eq!((second, notice), (first, None));
+        let mut backups: Vec<String> = fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.ends_with(".bak"))
+            .collect();
+        backups.sort();
+        assert_eq!(backups, vec!["settings.json.20261018-120000.bak"]);
+        assert_eq!(fs::read_to_string(dir.join(&backups[0])).unwrap(), newer);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn bad_version_numbers_are_reported() {
+        let loaded: Loaded<Settings> = parse(r#"{"schema_version": "one", "fps": 12}"#, LIMITS);
+        assert_eq!(loaded.settings.fps, 12);
+        assert_eq!(loaded.problems, vec![field(VERSION_FIELD, "is not a version number: \"one\"")]);
+    }
+
+    #[test]
+    fn notice_names_every_problem_and_the_backup() {
+        let problems = [field("fps", "must be between 1 and 30, not 0"), Problem::Newer(2)];
+        assert_eq!(
+            notice(&problems, Some("settings.json.20261018-120000.bak")),
+            "Some settings could not be loaded:\n\
+             • \"fps\" must be between 1 and 30, not 0, so it was reset.\n\
+             • The file is from a newer WinHider (settings version 2); settings this version does not know were dropped.\n\
+             The original file was kept as settings.json.20261018-120000.bak."
+        );
+        assert!(notice(&problems, None).ends_with("left as it is."));
+    }
+}
@@ -0,0 +1,265 @@
+/*
+ * =============================================================================
+ * WinHider SemVer - Release Version Parsing and Ordering
+ * =============================================================================
+ *
+ * Filename: semver.rs
+ * Description: Parses release tags as Semantic Versions 2.0.0 and orders
+ *              them, so the updater only offers releases that really are
+ *              newer.
+ *
+ * Notes:
+ * - A leading `v` is accepted, as release tags use one (`v1.2.0`). Missing
+ *   minor and patch numbers count as 0, so `v1.10` is `1.10.0`.
+ * - Pre-releases sort before their release (`1.2.0-beta.1 < 1.2.0`), and
+ *   pre-release identifiers compare numerically when both are numbers,
+ *   otherwise as ASCII text, with numbers first.
+ * - Build metadata (`+build.5`) is kept for display but ignored for ordering
+ *   and equality.
+ * - Anything else is an error rather than a guess.
+ * =============================================================================
+ */
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// One dot-separated pre-release identifier.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Identifier {
+    Numeric(u64),
+    Alpha(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre: Vec<Identifier>,
+    pub build: Option<String>,
+}
+
+fn is_identifier(part: &str) -> bool {
+    !part.is_empty() && part.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+fn parse_number(part: &str, what: &str, tag: &str) -> Result<u64, String> {
+    if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("Invalid {} version in \"{}\"", what, tag));
+    }
+    if part.len() > 1 && part.starts_with('0') {
+        return Err(format!("Leading zero in the {} version of \"{}\"", what, tag));
+    }
+    part.parse().map_err(|_| format!("The {} version of \"{}\" is too large", what, tag))
+}
+
+impl FromStr for Version {
+    type Err = String;
+
+    fn from_str(tag: &str) -> Result<Self, String> {
+        let text = tag.trim();
+        let text = text.strip_prefix(['v', 'V']).unwrap_or(text);
+
+        let (text, build) = match text.split_once('+') {
+            Some((rest, build)) => {
+                if !build.split('.').all(is_identifier) {
+                    return Err(format!("Invalid build metadata in \"{}\"", tag));
+                }
+                (rest, Some(build.to_string()))
+            }
+            None => (text, None),
+        };
+
+        let (core, pre) = match text.split_once('-') {
+            Some((core, pre)) => {
+                let pre = pre.split('.')
+                    .map(|part| {
+                        if !is_identifier(part) {
+                            Err(format!("Invalid pre-release in \"{}\"", tag))
+                        } else if part.bytes().all(|b| b.is_ascii_digit()) {
+                            parse_number(part, "pre-release", tag).map(Identifier::Numeric)
+                        } else {
+                            Ok(Identifier::Alpha(part.to_string()))
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                (core, pre)
+            }
+            None => (text, Vec::new()),
+        };
+
+        let parts: Vec<&str> = core.split('.').collect();
+        if parts.len() > 3 {
+            return Err(format!("Too many version numbers in \"{}\"", tag));
+        }
+        let number = |i: usize, what: &str| parts.get(i).map_or(Ok(0), |part| parse_number(part, what, tag));
+
+        Ok(Version {
+            major: number(0, "major")?,
+            minor: number(1, "minor")?,
+            patch: number(2, "patch")?,
+            pre,
+            build,
+        })
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Identifier::Numeric(n) => write!(f, "{}", n),
+            Identifier::Alpha(s) => f.write_str(s),
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        for (i, id) in self.pre.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { '-' } else { '.' }, id)?;
+        }
+        if let Some(build) = &self.build {
+            write!(f, "+{}", build)?;
+        }
+        Ok(())
+    }
+}
+
+impl Ord for Identifier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Identifier::Numeric(a), Identifier::Numeric(b)) => a.cmp(b),
+            (Identifier::Numeric(_), Identifier::Alpha(_)) => Ordering::Less,
+            (Identifier::Alpha(_), Identifier::Numeric(_)) => Ordering::Greater,
+            (Identifier::Alpha(a), Identifier::Alpha(b)) => a.cmp(b),
+        }
+    }
+}
+
+impl PartialOrd for Identifier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                // A release outranks its pre-releases
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                // A longer list wins when all shared identifiers are equal
+                (false, false) => self.pre.cmp(&other.pre),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+/// Whether release tag `candidate` is newer than `current`.
+pub fn is_newer(current: &str, candidate: &str) -> Result<bool, String> {
+    Ok(candidate.parse::<Version>()? > current.parse::<Version>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(tag: &str) -> Version {
+        tag.parse().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    #[test]
+    fn comparison_table() {
+        use Ordering::*;
+        let cases = [
+            ("1.0.0", "1.0.0", Equal),
+            ("v1.0.0", "1.0.0", Equal),
+            ("1.0.0", "2.0.0", Less),
+            ("2.0.0", "2.1.0", Less),
+            ("2.1.0", "2.1.1", Less),
+            ("v1.9.0", "v1.10.0", Less),
+            ("v1.10", "v1.9-rc", Greater),
+            ("v1.2", "v1.2.0", Equal),
+            ("v1", "v1.0.0", Equal),
+            ("v1.2.0-beta.1", "v1.2.0", Less),
+            ("v1.2.0", "v1.2.1-alpha", Less),
+            // The precedence example from the SemVer 2.0.0 specification
+            ("1.0.0-alpha", "1.0.0-alpha.1", Less),
+            ("1.0.0-alpha.1", "1.0.0-alpha.beta", Less),
+            ("1.0.0-alpha.beta", "1.0.0-beta", Less),
+            ("1.0.0-beta", "1.0.0-beta.2", Less),
+            ("1.0.0-beta.2", "1.0.0-beta.11", Less),
+            ("1.0.0-beta.11", "1.0.0-rc.1", Less),
+            ("1.0.0-rc.1", "1.0.0", Less),
+            // Identifiers compare as ASCII, so upper case sorts first
+            ("1.0.0-RC.1", "1.0.0-rc.1", Less),
+            ("1.0.0-x-y", "1.0.0-x-z", Less),
+            // Build metadata never matters
+            ("1.0.0+build.1", "1.0.0+build.2", Equal),
+            ("1.0.0-rc.1+001", "1.0.0-rc.1", Equal),
+            ("1.0.0+zzz", "1.0.1", Less),
+        ];
+        for (a, b, expected) in cases {
+            assert_eq!(v(a).cmp(&v(b)), expected, "{} vs {}", a, b);
+            assert_eq!(v(b).cmp(&v(a)), expected.reverse(), "{} vs {}", b, a);
+        }
+    }
+
+    #[test]
+    fn parses_every_part() {
+        let version = v("v1.2.3-beta.4.x-y+exp.sha.5114f85");
+        assert_eq!((version.major, version.minor, version.patch), (1, 2, 3));
+        assert_eq!(version.pre, vec![
+            Identifier::Alpha("beta".to_string()),
+            Identifier::Numeric(4),
+            Identifier::Alpha("x-y".to_string()),
+        ]);
+        assert_eq!(version.build.as_deref(), Some("exp.sha.5114f85"));
+        assert_eq!(version.to_string(), "1.2.3-beta.4.x-y+exp.sha.5114f85");
+    }
+
+    #[test]
+    fn unparsable_tags_are_errors() {
+        for tag in [
+            "", "v", "latest", "1.x.0", "1..0", "1.0.0.0", "1.0.0-", "1.0.0-beta..1", "1.0.0-beta_1",
+            "1.0.0+", "1.0.0+bad!", "01.0.0", "1.02.0", "1.0.0-01", "-1.0.0", "1.0.0 beta",
+            "99999999999999999999.0.0",
+        ] {
+            assert!(tag.parse::<Version>().is_err(), "{:?} should not parse", tag);
+        }
+    }
+
+    #[test]
+    fn leading_zeros_are_allowed_inside_alphanumerics() {
+        assert_eq!(v("1.0.0-0abc").pre, vec![Identifier::Alpha("0abc".to_string())]);
+        assert_eq!(v("1.0.0-0").pre, vec![Identifier::Numeric(0)]);
+        assert_eq!(v("1.0.0+001").build.as_deref(), Some("001"));
+    }
+
+    #[test]
+    fn is_newer_reports_bad_tags() {
+        assert_eq!(is_newer("v1.0.1", "v1.1.0"), Ok(true));
+        assert_eq!(is_newer("v1.2.0", "v1.2.0-beta.1"), Ok(false));
+        assert_eq!(is_newer("v1.2.0", "v1.2.0+rebuild"), Ok(false));
+        assert!(is_newer("v1.0.1", "nightly").is_err());
+        assert!(is_newer("garbage", "v1.0.0").is_err());
+    }
+}
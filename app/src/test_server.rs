@@ -0,0 +1,117 @@
+/*
+ * =============================================================================
+ * WinHider Test Server - Local HTTP Server for Tests
+ * =============================================================================
+ *
+ * Filename: test_server.rs
+ * Description: Answers HTTP requests on a local port so that update checks,
+ *              installer downloads and proxy settings can be tested without
+ *              the network.
+ *
+ * Notes:
+ * - Every request comes on its own connection and is answered with
+ *   `Connection: close`. The server stops after the number of requests it
+ *   was started for and hands back what it received.
+ * - `Content-Length` is the body's length unless the reply sets another,
+ *   which plays a connection that drops part way through.
+ * - A client sends a proxy the same requests with absolute URLs, or a
+ *   `CONNECT`, so the server stands in for a proxy as well.
+ * =============================================================================
+ */
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread::JoinHandle;
+
+/// A request the server received.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Request {
+    /// Request line without the line break, e.g. `GET /releases HTTP/1.1`.
+    pub line: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Value of the header `name`, which is matched ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+}
+
+/// The answer to one request.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Reply {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Reply {
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Reply {
+        Reply { status, headers: Vec::new(), body: body.into() }
+    }
+
+    pub fn header(mut self, name: &str, value: impl ToString) -> Reply {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// A server running on a thread of its own.
+pub struct Server {
+    /// `http://127.0.0.1:<port>`
+    pub base: String,
+    pub port: u16,
+    handle: JoinHandle<Vec<Request>>,
+}
+
+impl Server {
+    /// Answers `count` requests with what `respond` returns for each.
+    pub fn start(count: usize, mut respond: impl FnMut(&Request) -> Reply + Send + 'static) -> Server {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = std::thread::spawn(move || {
+            (0..count).map(|_| {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let mut headers = Vec::new();
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 2 {
+                    if let Some((name, value)) = header.split_once(':') {
+                        headers.push((name.trim().to_string(), value.trim().to_string()));
+                    }
+                    header.clear();
+                }
+                let request = Request { line: line.trim_end().to_string(), headers };
+
+                let reply = respond(&request);
+                let mut head = format!("HTTP/1.1 {} Stub\r\n", reply.status);
+                for (name, value) in &reply.headers {
+                    head.push_str(&format!("{}: {}\r\n", name, value));
+                }
+                if !reply.headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("Content-Length")) {
+                    head.push_str(&format!("Content-Length: {}\r\n", reply.body.len()));
+                }
+                head.push_str("Connection: close\r\n\r\n");
+                let _ = stream.write_all(head.as_bytes());
+                let _ = stream.write_all(&reply.body);
+                request
+            }).collect()
+        });
+        Server { base: format!("http://127.0.0.1:{}", port), port, handle }
+    }
+
+    /// Answers one request with each of `replies` in turn.
+    pub fn replying(replies: Vec<Reply>) -> Server {
+        let count = replies.len();
+        let mut replies = replies.into_iter();
+        Server::start(count, move |_| replies.next().unwrap())
+    }
+
+    /// Waits for the last request and returns every request in order.
+    pub fn join(self) -> Vec<Request> {
+        self.handle.join().unwrap()
+    }
+}
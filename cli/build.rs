@@ -14,14 +14,78 @@
  * 2. UAC Enforcement: Injects an XML manifest to strictly enforce "Run as
  * Administrator" privileges. This ensures the app triggers
  * a UAC prompt immediately upon startup.
+ * 3. Long Paths: Declares the executable longPathAware so config and payload
+ * paths beyond MAX_PATH work when long paths are enabled system-wide.
+ * 4. Build Metadata: Bakes the release version (from appver.txt), the git
+ * commit and the build date into the executable as WINHIDER_VERSION,
+ * WINHIDER_COMMIT and WINHIDER_BUILD_DATE, so they no longer depend on
+ * the working directory at run time. SOURCE_DATE_EPOCH overrides the
+ * build date for reproducible builds.
  *
  * Dependencies:
  * - winres: Used to compile Windows resource files (.rc) and link them.
  * =============================================================================
  */
 
- 
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Release version from appver.txt at the workspace root, or the crate
+/// version when it is missing.
+fn version() -> String {
+    std::fs::read_to_string("../appver.txt")
+        .map(|text| text.trim().to_string())
+        .ok()
+        .filter(|version| !version.is_empty())
+        .unwrap_or_else(|| format!("v{}", env!("CARGO_PKG_VERSION")))
+}
+
+/// Short hash of the commit being built, or "unknown" outside a git checkout.
+fn commit() -> String {
+    Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .filter(|hash| !hash.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// UTC build date as YYYY-MM-DD.
+fn build_date() -> String {
+    let seconds = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse::<i64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+        });
+
+    // Days since 1970-01-01 to a civil date (Howard Hinnant's algorithm)
+    let z = seconds.div_euclid(86_400) + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
 fn main() {
+    // 4. Build metadata, refreshed when the version or the checked out commit changes
+    println!("cargo:rustc-env=WINHIDER_VERSION={}", version());
+    println!("cargo:rustc-env=WINHIDER_COMMIT={}", commit());
+    println!("cargo:rustc-env=WINHIDER_BUILD_DATE={}", build_date());
+    println!("cargo:rerun-if-changed=../appver.txt");
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs/heads");
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
     if cfg!(target_os = "windows") {
         let mut res = winres::WindowsResource::new();
         
@@ -38,6 +102,11 @@ fn main() {
         </requestedPrivileges>
     </security>
 </trustInfo>
+<application xmlns="urn:schemas-microsoft-com:asm.v3">
+    <windowsSettings xmlns:ws2="http://schemas.microsoft.com/SMI/2016/WindowsSettings">
+        <ws2:longPathAware>true</ws2:longPathAware>
+    </windowsSettings>
+</application>
 </assembly>
 "#);
 
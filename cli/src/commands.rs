@@ -0,0 +1,532 @@
+/*
+ * =============================================================================
+ * WinHider CLI - Command Mode
+ * =============================================================================
+ *
+ * Filename: commands.rs
+ * Description: Headless command mode for the CLI binary. Without a subcommand
+ *              the binary keeps opening the GUI; with one it runs the action,
+ *              prints to the parent console and exits with a status code.
+ *
+ * Exit Codes:
+ * - 0  Success
+ * - 1  General error
+ * - 2  Invalid arguments (reported by clap)
+ * - 3  No window matched the target
+ * - 4  Operation failed
+ * - 5  Target requires an elevated (administrator) WinHider
+ * - 6  Verification could not decide for at least one window
+ * =============================================================================
+ */
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use clap::{Args, Parser, Subcommand};
+use windows::Win32::Foundation::*;
+use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+use crate::dryrun;
+use crate::elevation;
+use crate::injector::{self, InjectionEvent, InjectionPool, TaskOutcome};
+use crate::frame::RgbaFrame;
+use crate::planner::InjectionTask;
+use crate::recording::{self, ClipFormat, ClipSettings};
+use crate::verify::{self, Verdict};
+use crate::{
+    capture_one_frame, get_captures_dir, load_settings, load_settings_with_notice, query_window_state, record_clip,
+    verify_capture_hidden, InjectionAction, Monitor, Window, APP_VERSION, BUILD_COMMIT, BUILD_DATE,
+};
+
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_NO_MATCH: i32 = 3;
+pub const EXIT_FAILED: i32 = 4;
+pub const EXIT_ELEVATION_REQUIRED: i32 = 5;
+pub const EXIT_INCONCLUSIVE: i32 = 6;
+
+/// What `--version` prints: version, commit and build date, as baked in by build.rs.
+const VERSION_LINE: &str = concat!(env!("WINHIDER_VERSION"), " (", env!("WINHIDER_COMMIT"), " ", env!("WINHIDER_BUILD_DATE"), ")");
+
+#[derive(Parser)]
+#[command(name = "winhider-cli", version = VERSION_LINE, about = "Hide windows from screen capture and the taskbar")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Restore the GUI session saved before an elevated relaunch
+    #[arg(long, hide = true)]
+    pub restore_session: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List visible windows with the integrity level of their process
+    List,
+    /// Hide matching windows from screen capture and/or the taskbar
+    Hide(TargetArgs),
+    /// Make matching windows visible again
+    Show(TargetArgs),
+    /// Check that matching windows are really excluded from screen capture
+    Verify(VerifyArgs),
+    /// Save one frame of what a monitor or window looks like to capture, as PNG
+    Snapshot(SourceArgs),
+    /// Record a short GIF or APNG clip of what a monitor or window looks like to capture
+    Record(RecordArgs),
+    /// Print the version, git commit and build date
+    Version(VersionArgs),
+}
+
+#[derive(Args)]
+#[group(id = "target", required = true, multiple = false, args = ["hwnd", "pid", "title"])]
+pub struct WindowSelector {
+    /// Window handle, as printed by `list`
+    #[arg(long, value_parser = parse_hwnd)]
+    pub hwnd: Option<isize>,
+
+    /// Process ID owning the windows
+    #[arg(long)]
+    pub pid: Option<u32>,
+
+    /// Case-insensitive substring of the window title
+    #[arg(long)]
+    pub title: Option<String>,
+}
+
+impl WindowSelector {
+    fn matches(&self, w: &ListedWindow) -> bool {
+        if let Some(hwnd) = self.hwnd {
+            w.hwnd.0 == hwnd
+        } else if let Some(pid) = self.pid {
+            w.pid == pid
+        } else if let Some(title) = &self.title {
+            w.title.to_lowercase().contains(&title.to_lowercase())
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct TargetArgs {
+    #[command(flatten)]
+    pub select: WindowSelector,
+
+    /// Only change screen capture visibility
+    #[arg(long)]
+    pub capture: bool,
+
+    /// Only change taskbar visibility
+    #[arg(long)]
+    pub taskbar: bool,
+
+    /// Always inject, even for actions an external call could perform
+    #[arg(long)]
+    pub inject: bool,
+
+    /// Print the windows that would change as JSON without changing anything
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(serde::Serialize)]
+struct DryRunReport {
+    command: &'static str,
+    changes: Vec<dryrun::WindowDiff>,
+    /// PIDs skipped because they need an elevated WinHider
+    blocked_pids: Vec<u32>,
+}
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    #[command(flatten)]
+    pub select: WindowSelector,
+
+    /// Print the results as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args)]
+#[group(id = "source", multiple = false, args = ["monitor", "hwnd"])]
+pub struct SourceArgs {
+    /// Monitor number as shown in the GUI, starting at 1 (default: primary)
+    #[arg(long)]
+    pub monitor: Option<usize>,
+
+    /// Capture a single window instead of a monitor
+    #[arg(long, value_parser = parse_hwnd)]
+    pub hwnd: Option<isize>,
+
+    /// Output file (default: the captures folder)
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct RecordArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    /// Frames per second (default: GUI setting)
+    #[arg(long)]
+    pub fps: Option<u32>,
+
+    /// Length in seconds (default: GUI setting)
+    #[arg(long)]
+    pub duration: Option<u32>,
+
+    /// `gif` or `apng` (default: from the output extension, then the GUI setting)
+    #[arg(long, value_parser = parse_clip_format)]
+    pub format: Option<ClipFormat>,
+}
+
+enum CaptureSource {
+    Monitor(Monitor),
+    Window(Window),
+}
+
+#[derive(Args)]
+pub struct VersionArgs {
+    /// Print the build metadata as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(serde::Serialize)]
+struct BuildInfo {
+    version: &'static str,
+    commit: &'static str,
+    build_date: &'static str,
+}
+
+#[derive(serde::Serialize)]
+struct VerifyResult {
+    hwnd: isize,
+    pid: u32,
+    title: String,
+    verdict: Verdict,
+    detail: String,
+}
+
+struct ListedWindow {
+    hwnd: HWND,
+    pid: u32,
+    title: String,
+}
+
+/// Reconnects stdout/stderr to the console we were started from. The binary
+/// uses the windows subsystem, so without this nothing would be printed.
+pub fn attach_parent_console() {
+    unsafe {
+        let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+pub fn run(command: Command) -> i32 {
+    // A damaged settings file is repaired once, so say what changed now
+    if let (_, Some(notice)) = load_settings_with_notice() {
+        eprintln!("{}", notice);
+    }
+    match command {
+        Command::List => list(),
+        Command::Hide(target) => apply(&target, true),
+        Command::Show(target) => apply(&target, false),
+        Command::Verify(args) => verify(&args),
+        Command::Snapshot(args) => snapshot(&args),
+        Command::Record(args) => record(&args),
+        Command::Version(args) => version(&args),
+    }
+}
+
+fn version(args: &VersionArgs) -> i32 {
+    let info = BuildInfo { version: APP_VERSION, commit: BUILD_COMMIT, build_date: BUILD_DATE };
+    if args.json {
+        match serde_json::to_string_pretty(&info) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to serialize build info: {}", e);
+                return EXIT_FAILED;
+            }
+        }
+    } else {
+        println!("winhider-cli {}", VERSION_LINE);
+    }
+    EXIT_OK
+}
+
+fn list() -> i32 {
+    let current = elevation::current_token();
+    println!("{:<12} {:<8} {:<20} TITLE", "HWND", "PID", "INTEGRITY");
+    for w in list_windows() {
+        let token = elevation::query_process_token(w.pid);
+        let integrity = token.map(|t| t.label()).unwrap_or_else(|| "Unknown".to_string());
+        let badge = if elevation::requires_admin(&current, token.as_ref()) { " (requires admin)" } else { "" };
+        println!("{:<12} {:<8} {:<20} {}{}", format!("{:#x}", w.hwnd.0), w.pid, integrity, w.title, badge);
+    }
+    EXIT_OK
+}
+
+fn apply(target: &TargetArgs, hide: bool) -> i32 {
+    let windows = list_windows();
+    let matches: Vec<&ListedWindow> = windows.iter().filter(|w| target.select.matches(w)).collect();
+
+    if matches.is_empty() {
+        eprintln!("No matching window found.");
+        return EXIT_NO_MATCH;
+    }
+
+    // Neither flag given means both
+    let (capture, taskbar) = match (target.capture, target.taskbar) {
+        (false, false) => (true, true),
+        flags => flags,
+    };
+
+    let mut actions = Vec::new();
+    if capture {
+        actions.push(if hide { InjectionAction::HideCapture } else { InjectionAction::ShowCapture });
+    }
+    if taskbar {
+        actions.push(if hide { InjectionAction::HideTaskbar } else { InjectionAction::ShowTaskbar });
+    }
+
+    let mut code = EXIT_OK;
+    let mut blocked: Vec<u32> = Vec::new();
+    let mut tasks = Vec::new();
+    for w in &matches {
+        if blocked.contains(&w.pid) {
+            continue;
+        }
+        if elevation::pid_requires_admin(w.pid) {
+            eprintln!("PID {}: {}", w.pid, elevation::ELEVATION_REQUIRED);
+            code = EXIT_ELEVATION_REQUIRED;
+            blocked.push(w.pid);
+            continue;
+        }
+        tasks.extend(actions.iter().map(|&action| InjectionTask { hwnd: w.hwnd, pid: w.pid, action }));
+    }
+
+    let prefer_external = load_settings().prefer_external_strategies && !target.inject;
+
+    if target.dry_run {
+        // Diff against every listed window: an injection reaches all windows of the process
+        let states: Vec<dryrun::WindowState> = windows.iter()
+            .map(|w| {
+                let (capture_hidden, taskbar_hidden) = query_window_state(w.hwnd);
+                dryrun::WindowState { hwnd: w.hwnd, pid: w.pid, title: w.title.clone(), capture_hidden, taskbar_hidden }
+            })
+            .collect();
+        let report = DryRunReport {
+            command: if hide { "hide" } else { "show" },
+            changes: dryrun::diff(&states, &tasks, prefer_external, std::process::id()),
+            blocked_pids: blocked,
+        };
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to serialize dry run: {}", e);
+                return EXIT_FAILED;
+            }
+        }
+        return code;
+    }
+
+    // Same job API as the GUI; the CLI just waits for the results
+    let mut pool = InjectionPool::new(injector::default_workers(), prefer_external, || {});
+    let job = pool.submit(tasks);
+    while let Some(event) = pool.recv() {
+        match event {
+            InjectionEvent::Task(result) => {
+                let pid = result.injection.pid;
+                match result.outcome {
+                    TaskOutcome::Done => {
+                        let via: Vec<String> = result.applied.iter()
+                            .map(|(action, strategy)| format!("{} via {}", action_name(*action), strategy.label()))
+                            .collect();
+                        for w in matches.iter().filter(|w| w.pid == pid) {
+                            println!("PID {}: {} ({:#x}) [{}]", pid, w.title, w.hwnd.0, via.join(", "));
+                        }
+                    }
+                    TaskOutcome::Failed(e) => {
+                        eprintln!("PID {}: {}", pid, e);
+                        if code == EXIT_OK {
+                            code = EXIT_FAILED;
+                        }
+                    }
+                    TaskOutcome::Cancelled => {}
+                }
+            }
+            InjectionEvent::Finished(summary) if summary.job_id == job.id() => break,
+            InjectionEvent::Finished(_) => {}
+        }
+    }
+    code
+}
+
+fn verify(args: &VerifyArgs) -> i32 {
+    let matches: Vec<ListedWindow> = list_windows().into_iter().filter(|w| args.select.matches(w)).collect();
+    if matches.is_empty() {
+        eprintln!("No matching window found.");
+        return EXIT_NO_MATCH;
+    }
+
+    let results: Vec<VerifyResult> = matches
+        .into_iter()
+        .map(|w| {
+            let verification = verify_capture_hidden(w.hwnd).unwrap_or_else(|e| verify::Verification::inconclusive(&e));
+            VerifyResult { hwnd: w.hwnd.0, pid: w.pid, title: w.title, verdict: verification.verdict, detail: verification.detail }
+        })
+        .collect();
+
+    if args.json {
+        match serde_json::to_string_pretty(&results) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to serialize results: {}", e);
+                return EXIT_FAILED;
+            }
+        }
+    } else {
+        for r in &results {
+            println!("PID {}: {} ({:#x}) {} - {}", r.pid, r.title, r.hwnd, r.verdict.label(), r.detail);
+        }
+    }
+
+    // A visible window outranks one that could not be checked
+    if results.iter().any(|r| r.verdict == Verdict::Visible) {
+        EXIT_FAILED
+    } else if results.iter().any(|r| r.verdict == Verdict::Inconclusive) {
+        EXIT_INCONCLUSIVE
+    } else {
+        EXIT_OK
+    }
+}
+
+fn capture_source(args: &SourceArgs) -> Result<CaptureSource, String> {
+    if let Some(hwnd) = args.hwnd {
+        if unsafe { !IsWindow(HWND(hwnd)).as_bool() } {
+            return Err(format!("No window with handle {:#x}", hwnd));
+        }
+        return Ok(CaptureSource::Window(Window::from_raw_hwnd(hwnd as *mut std::ffi::c_void)));
+    }
+    match args.monitor {
+        Some(n) => {
+            let monitors = Monitor::enumerate().map_err(|_| "Failed to list monitors".to_string())?;
+            n.checked_sub(1)
+                .and_then(|i| monitors.get(i).copied())
+                .map(CaptureSource::Monitor)
+                .ok_or_else(|| format!("No monitor {} (found {})", n, monitors.len()))
+        }
+        None => Monitor::primary().map(CaptureSource::Monitor).map_err(|_| "No primary monitor".to_string()),
+    }
+}
+
+fn output_path(args: &SourceArgs, prefix: &str, extension: &str) -> PathBuf {
+    args.output.clone().unwrap_or_else(|| {
+        get_captures_dir().join(format!("{}_{}.{}", prefix, chrono::Local::now().format("%Y%m%d_%H%M%S"), extension))
+    })
+}
+
+fn snapshot(args: &SourceArgs) -> i32 {
+    let frame = match capture_source(args) {
+        Ok(CaptureSource::Monitor(m)) => capture_one_frame(m),
+        Ok(CaptureSource::Window(w)) => capture_one_frame(w),
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_NO_MATCH;
+        }
+    };
+    let path = output_path(args, "snapshot", "png");
+    match frame.and_then(|f| recording::save_snapshot(&path, &f)) {
+        Ok(()) => {
+            println!("{}", path.display());
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            EXIT_FAILED
+        }
+    }
+}
+
+fn record(args: &RecordArgs) -> i32 {
+    let saved = load_settings();
+    let settings = ClipSettings::new(args.fps.unwrap_or(saved.clip_fps), args.duration.unwrap_or(saved.clip_duration_secs));
+    let format = args.format
+        .or_else(|| args.source.output.as_deref().and_then(ClipFormat::from_path))
+        .unwrap_or(saved.clip_format);
+
+    let source = match capture_source(&args.source) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_NO_MATCH;
+        }
+    };
+    eprintln!("Recording {}s at {} fps...", settings.duration_secs, settings.fps);
+    let frames: Result<Vec<Arc<RgbaFrame>>, String> = match source {
+        CaptureSource::Monitor(m) => record_clip(m, settings),
+        CaptureSource::Window(w) => record_clip(w, settings),
+    };
+
+    let path = output_path(&args.source, "clip", format.extension());
+    match frames.and_then(|frames| recording::save_clip(&path, format, &frames, settings.fps)) {
+        Ok(()) => {
+            println!("{}", path.display());
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            EXIT_FAILED
+        }
+    }
+}
+
+fn parse_clip_format(value: &str) -> Result<ClipFormat, String> {
+    ClipFormat::from_path(Path::new(&format!("clip.{}", value)))
+        .ok_or_else(|| format!("unknown clip format: {} (expected gif or apng)", value))
+}
+
+fn action_name(action: InjectionAction) -> &'static str {
+    match action {
+        InjectionAction::HideCapture => "hide capture",
+        InjectionAction::ShowCapture => "show capture",
+        InjectionAction::HideTaskbar => "hide taskbar",
+        InjectionAction::ShowTaskbar => "show taskbar",
+    }
+}
+
+fn parse_hwnd(value: &str) -> Result<isize, String> {
+    let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => isize::from_str_radix(hex, 16),
+        None => value.parse::<isize>(),
+    };
+    parsed.map_err(|_| format!("invalid window handle: {}", value))
+}
+
+fn list_windows() -> Vec<ListedWindow> {
+    let mut list: Vec<ListedWindow> = Vec::new();
+
+    unsafe extern "system" fn enum_proc(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        unsafe {
+            if !IsWindowVisible(hwnd).as_bool() { return BOOL(1); }
+
+            let mut title_buf = [0u16; 256];
+            let len = GetWindowTextW(hwnd, &mut title_buf);
+            if len == 0 { return BOOL(1); }
+
+            let title = String::from_utf16_lossy(&title_buf[..len as usize]);
+            if crate::IGNORED_WINDOWS.contains(&title.as_str()) { return BOOL(1); }
+
+            let mut pid = 0;
+            GetWindowThreadProcessId(hwnd, Some(&mut pid));
+
+            let list = &mut *(lparam.0 as *mut Vec<ListedWindow>);
+            list.push(ListedWindow { hwnd, pid, title });
+            BOOL(1)
+        }
+    }
+
+    unsafe { let _ = EnumWindows(Some(enum_proc), LPARAM(&mut list as *mut _ as isize)); }
+    list
+}
@@ -0,0 +1,159 @@
+/*
+ * =============================================================================
+ * WinHider Displays - Monitor Descriptions and Matching
+ * =============================================================================
+ *
+ * Filename: displays.rs
+ * Description: Describes monitors for the preview's source selector and finds
+ *              the same physical monitor again after the display setup
+ *              changes (docking, undocking, a cable pulled).
+ *
+ * Notes:
+ * - Windows renumbers `\\.\DISPLAYn` device names when monitors come and go,
+ *   so the device name alone does not identify a monitor. The friendly name
+ *   (the model, from the monitor's EDID) does, unless two identical models are
+ *   attached; then the device name and the desktop position break the tie.
+ * - A monitor of a different model on the same connector is a different
+ *   monitor.
+ * =============================================================================
+ */
+
+use crate::verify::ScreenRect;
+
+/// What the source selector shows about a monitor.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DisplayInfo {
+    /// GDI device name, such as `\\.\DISPLAY1`.
+    pub device_name: String,
+    /// Model name; empty when Windows does not report one.
+    pub friendly_name: String,
+    /// Position and size on the virtual desktop, in physical pixels.
+    pub rect: ScreenRect,
+    pub primary: bool,
+    /// Display scaling, 100 for 96 DPI.
+    pub scale_percent: u32,
+}
+
+impl DisplayInfo {
+    /// Model name, or the device name without its `\\.\` prefix.
+    pub fn name(&self) -> &str {
+        if self.friendly_name.is_empty() {
+            self.device_name.trim_start_matches(r"\\.\")
+        } else {
+            &self.friendly_name
+        }
+    }
+
+    /// Short label for monitor `number` (1-based), for the closed selector.
+    pub fn short_label(&self, number: usize) -> String {
+        format!("Monitor {}: {}", number, self.name())
+    }
+
+    /// Full description for monitor `number` (1-based), for the open list.
+    pub fn label(&self, number: usize) -> String {
+        let mut label = format!(
+            "{}  {}×{} at ({}, {})  {}%",
+            self.short_label(number),
+            self.rect.width(),
+            self.rect.height(),
+            self.rect.left,
+            self.rect.top,
+            self.scale_percent,
+        );
+        if self.primary {
+            label.push_str("  primary");
+        }
+        label
+    }
+}
+
+/// Scaling percentage for an effective DPI.
+pub fn scale_percent(dpi: u32) -> u32 {
+    (dpi * 100 + 48) / 96
+}
+
+/// Index in `current` of the monitor `previous` described, if it is still
+/// attached.
+pub fn find_same(previous: &DisplayInfo, current: &[DisplayInfo]) -> Option<usize> {
+    let same_model = |d: &DisplayInfo| previous.friendly_name.is_empty() || d.friendly_name == previous.friendly_name;
+    let candidates: Vec<usize> = (0..current.len()).filter(|&i| same_model(&current[i])).collect();
+
+    // Same connector, then same place on the desktop; the only monitor of
+    // its model is the one even when Windows renumbered it
+    candidates.iter().copied().find(|&i| current[i].device_name == previous.device_name)
+        .or_else(|| candidates.iter().copied().find(|&i| current[i].rect == previous.rect))
+        .or_else(|| (candidates.len() == 1 && !previous.friendly_name.is_empty()).then(|| candidates[0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(device: u32, name: &str, left: i32, width: i32, primary: bool) -> DisplayInfo {
+        DisplayInfo {
+            device_name: format!(r"\\.\DISPLAY{}", device),
+            friendly_name: name.to_string(),
+            rect: ScreenRect { left, top: 0, right: left + width, bottom: width * 9 / 16 },
+            primary,
+            scale_percent: 100,
+        }
+    }
+
+    #[test]
+    fn labels_describe_the_monitor() {
+        let mut d = display(2, "DELL U2720Q", -3840, 3840, false);
+        d.scale_percent = 150;
+        assert_eq!(d.short_label(2), "Monitor 2: DELL U2720Q");
+        assert_eq!(d.label(2), "Monitor 2: DELL U2720Q  3840×2160 at (-3840, 0)  150%");
+
+        let laptop = display(1, "", 0, 1920, true);
+        assert_eq!(laptop.label(1), r"Monitor 1: DISPLAY1  1920×1080 at (0, 0)  100%  primary");
+    }
+
+    #[test]
+    fn dpi_maps_to_windows_scaling_steps() {
+        for (dpi, percent) in [(96, 100), (120, 125), (144, 150), (168, 175), (192, 200), (288, 300)] {
+            assert_eq!(scale_percent(dpi), percent);
+        }
+    }
+
+    #[test]
+    fn finds_the_same_monitor_after_a_display_change() {
+        let laptop = display(1, "Built-in Display", 0, 1920, true);
+        let dell = display(2, "DELL U2720Q", 1920, 3840, false);
+        let lg = display(3, "LG 27GL850", 5760, 2560, false);
+
+        // (what was selected, what is attached now, expected index)
+        let cases: Vec<(&str, DisplayInfo, Vec<DisplayInfo>, Option<usize>)> = vec![
+            ("unchanged", dell.clone(), vec![laptop.clone(), dell.clone(), lg.clone()], Some(1)),
+            ("another monitor unplugged", lg.clone(), vec![laptop.clone(), display(2, "LG 27GL850", 1920, 2560, false)], Some(1)),
+            ("renumbered and moved after docking", dell.clone(), vec![display(3, "DELL U2720Q", -3840, 3840, false), laptop.clone()], Some(0)),
+            ("unplugged", dell.clone(), vec![laptop.clone()], None),
+            ("different model on the same connector", dell.clone(), vec![laptop.clone(), display(2, "HP E24", 1920, 1920, false)], None),
+            (
+                "two identical models, by connector",
+                display(3, "DELL U2720Q", 5760, 3840, false),
+                vec![display(2, "DELL U2720Q", 1920, 3840, false), display(3, "DELL U2720Q", 5760, 3840, false)],
+                Some(1),
+            ),
+            (
+                "two identical models renumbered, by position",
+                display(2, "DELL U2720Q", 1920, 3840, false),
+                vec![display(4, "DELL U2720Q", 5760, 3840, false), display(5, "DELL U2720Q", 1920, 3840, false)],
+                Some(1),
+            ),
+            (
+                "two identical models renumbered and moved",
+                display(2, "DELL U2720Q", 1920, 3840, false),
+                vec![display(4, "DELL U2720Q", 0, 3840, false), display(5, "DELL U2720Q", 3840, 3840, false)],
+                None,
+            ),
+            ("no model name, same connector", display(1, "", 0, 1920, true), vec![display(1, "", 0, 2560, true)], Some(0)),
+            ("no model name, renumbered", display(1, "", 0, 1920, true), vec![display(2, "", 1920, 1920, false)], None),
+        ];
+
+        for (name, previous, current, expected) in cases {
+            assert_eq!(find_same(&previous, &current), expected, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+/*
+ * =============================================================================
+ * WinHider Frame - Capture Buffer Conversion
+ * =============================================================================
+ *
+ * Filename: frame.rs
+ * Description: Turns a raw capture buffer into a tightly packed RGBA image the
+ *              preview can hand to egui. Capture surfaces are row-padded to
+ *              the GPU's pitch and may arrive as BGRA or RGBA.
+ *
+ * Guarantees:
+ * - Any row pitch at least as wide as a row is accepted; the padding is
+ *   dropped. The last row may stop right after its pixels.
+ * - Odd widths and heights are handled like any other size.
+ * - The output is always exactly `width * height * 4` bytes, or an error.
+ *   Nothing is skipped silently.
+ * =============================================================================
+ */
+
+use std::borrow::Cow;
+
+use crate::downscale::{self, Filter};
+
+const CHANNELS: usize = 4;
+
+/// Channel order of an 8-bit capture buffer.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PixelFormat {
+    Rgba8,
+    Bgra8,
+}
+
+/// Shape of a raw capture buffer.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FrameLayout {
+    pub width: u32,
+    pub height: u32,
+    /// Bytes from the start of one row to the start of the next.
+    pub row_pitch: usize,
+    pub format: PixelFormat,
+}
+
+/// Well-formed RGBA image: `pixels.len() == width * height * 4`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RgbaFrame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl FrameLayout {
+    fn row_bytes(&self) -> usize {
+        self.width as usize * CHANNELS
+    }
+
+    /// Smallest buffer that holds every pixel of this layout.
+    pub fn min_len(&self) -> usize {
+        if self.height == 0 {
+            return 0;
+        }
+        self.row_pitch * (self.height as usize - 1) + self.row_bytes()
+    }
+
+    fn validate(&self, len: usize) -> Result<(), String> {
+        if self.width == 0 || self.height == 0 {
+            return Err("Frame size must not be zero".to_string());
+        }
+        if self.row_pitch < self.row_bytes() {
+            return Err(format!("Row pitch {} is shorter than a {} pixel row", self.row_pitch, self.width));
+        }
+        if len < self.min_len() {
+            return Err(format!("Frame buffer too small: {} bytes for {}x{} with pitch {}",
+                len, self.width, self.height, self.row_pitch));
+        }
+        Ok(())
+    }
+}
+
+/// Tightly packed RGBA copy of `src`. Borrows when `src` already is one.
+pub fn to_rgba<'a>(src: &'a [u8], layout: &FrameLayout) -> Result<Cow<'a, [u8]>, String> {
+    layout.validate(src.len())?;
+
+    let row_bytes = layout.row_bytes();
+    let tight_len = row_bytes * layout.height as usize;
+    if layout.format == PixelFormat::Rgba8 && layout.row_pitch == row_bytes {
+        return Ok(Cow::Borrowed(&src[..tight_len]));
+    }
+
+    let mut out = Vec::with_capacity(tight_len);
+    for y in 0..layout.height as usize {
+        let start = y * layout.row_pitch;
+        let row = &src[start..start + row_bytes];
+        match layout.format {
+            PixelFormat::Rgba8 => out.extend_from_slice(row),
+            PixelFormat::Bgra8 => {
+                for px in row.chunks_exact(CHANNELS) {
+                    out.extend_from_slice(&[px[2], px[1], px[0], px[3]]);
+                }
+            }
+        }
+    }
+    Ok(Cow::Owned(out))
+}
+
+/// Converts `src` and shrinks it to fit `max_w` x `max_h` with `filter`.
+pub fn prepare_preview(src: &[u8], layout: &FrameLayout, max_w: u32, max_h: u32, filter: Filter) -> Result<RgbaFrame, String> {
+    let rgba = to_rgba(src, layout)?;
+    let (width, height) = downscale::fit_size(layout.width, layout.height, max_w.max(1), max_h.max(1));
+    let pixels = downscale::downscale(&rgba, layout.width, layout.height, width, height, filter)?;
+
+    if pixels.len() != width as usize * height as usize * CHANNELS {
+        return Err(format!("Converted frame has {} bytes, expected {}x{}", pixels.len(), width, height));
+    }
+    Ok(RgbaFrame { width, height, pixels })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Buffer whose pixel (x, y) is `[x, y, x ^ y, 255]` (as bytes) in the
+    /// given channel order, with `pad` junk bytes after every row.
+    fn pattern(width: u32, height: u32, pad: usize, format: PixelFormat) -> (Vec<u8>, FrameLayout) {
+        let row_pitch = width as usize * CHANNELS + pad;
+        let mut buf = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let (r, g, b) = (x as u8, y as u8, (x ^ y) as u8);
+                match format {
+                    PixelFormat::Rgba8 => buf.extend_from_slice(&[r, g, b, 255]),
+                    PixelFormat::Bgra8 => buf.extend_from_slice(&[b, g, r, 255]),
+                }
+            }
+            buf.extend(std::iter::repeat_n(0xAB, pad));
+        }
+        (buf, FrameLayout { width, height, row_pitch, format })
+    }
+
+    fn expected_rgba(width: u32, height: u32) -> Vec<u8> {
+        pattern(width, height, 0, PixelFormat::Rgba8).0
+    }
+
+    #[test]
+    fn packed_rgba_is_borrowed() {
+        let (buf, layout) = pattern(4, 3, 0, PixelFormat::Rgba8);
+        assert!(matches!(to_rgba(&buf, &layout).unwrap(), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn bgra_channels_are_swapped() {
+        let layout = FrameLayout { width: 1, height: 1, row_pitch: 4, format: PixelFormat::Bgra8 };
+        assert_eq!(to_rgba(&[1, 2, 3, 4], &layout).unwrap().as_ref(), &[3, 2, 1, 4]);
+    }
+
+    #[test]
+    fn last_row_may_omit_padding() {
+        let (mut buf, layout) = pattern(3, 2, 8, PixelFormat::Rgba8);
+        buf.truncate(layout.min_len());
+        assert_eq!(to_rgba(&buf, &layout).unwrap().as_ref(), expected_rgba(3, 2).as_slice());
+    }
+
+    #[test]
+    fn short_pitch_is_rejected() {
+        let layout = FrameLayout { width: 4, height: 2, row_pitch: 12, format: PixelFormat::Rgba8 };
+        assert!(to_rgba(&[0; 64], &layout).is_err());
+    }
+
+    #[test]
+    fn truncated_buffer_is_rejected() {
+        let (buf, layout) = pattern(5, 5, 4, PixelFormat::Bgra8);
+        assert!(to_rgba(&buf[..layout.min_len() - 1], &layout).is_err());
+        assert!(prepare_preview(&buf[..10], &layout, 640, 360, Filter::Box).is_err());
+    }
+
+    #[test]
+    fn zero_size_is_rejected() {
+        let layout = FrameLayout { width: 0, height: 4, row_pitch: 0, format: PixelFormat::Rgba8 };
+        assert!(to_rgba(&[], &layout).is_err());
+    }
+
+    fn format() -> impl Strategy<Value = PixelFormat> {
+        prop_oneof![Just(PixelFormat::Rgba8), Just(PixelFormat::Bgra8)]
+    }
+
+    fn filter() -> impl Strategy<Value = Filter> {
+        prop_oneof![Just(Filter::Nearest), Just(Filter::Box), Just(Filter::Bilinear)]
+    }
+
+    proptest! {
+        #[test]
+        fn conversion_drops_padding_and_fixes_order(
+            width in 1u32..70, height in 1u32..40, pad in 0usize..64, format in format(),
+        ) {
+            let (buf, layout) = pattern(width, height, pad, format);
+            let rgba = to_rgba(&buf, &layout).unwrap();
+            let expected = expected_rgba(width, height);
+            prop_assert_eq!(rgba.as_ref(), expected.as_slice());
+        }
+
+        #[test]
+        fn preview_is_always_well_formed(
+            width in 1u32..300, height in 1u32..200, pad in 0usize..64,
+            max_w in 0u32..200, max_h in 0u32..200, format in format(), filter in filter(),
+        ) {
+            let (buf, layout) = pattern(width, height, pad, format);
+            let frame = prepare_preview(&buf, &layout, max_w, max_h, filter).unwrap();
+            prop_assert!(frame.width >= 1 && frame.height >= 1);
+            prop_assert!(frame.width <= width && frame.height <= height);
+            prop_assert!(frame.width <= max_w.max(1) && frame.height <= max_h.max(1));
+            prop_assert_eq!(frame.pixels.len(), frame.width as usize * frame.height as usize * CHANNELS);
+            // Source is opaque, so every output pixel must be too
+            prop_assert!(frame.pixels.chunks_exact(CHANNELS).all(|px| px[3] == 255));
+        }
+
+        #[test]
+        fn short_buffers_never_panic(
+            width in 1u32..50, height in 1u32..50, pad in 0usize..16, cut in 1usize..200, format in format(),
+        ) {
+            let (buf, layout) = pattern(width, height, pad, format);
+            let len = layout.min_len().saturating_sub(cut);
+            prop_assert!(prepare_preview(&buf[..len], &layout, 32, 32, Filter::Bilinear).is_err());
+        }
+    }
+}
@@ -0,0 +1,144 @@
+/*
+ * =============================================================================
+ * WinHider Letterbox - Preview Fit Calculation
+ * =============================================================================
+ *
+ * Filename: letterbox.rs
+ * Description: Places a captured frame inside the preview pane at its true
+ *              aspect ratio, centred, with bars filling the rest.
+ *
+ * Notes:
+ * - The aspect ratio comes from the frame itself, so ultrawide, 16:10 and
+ *   portrait monitors all keep their shape. Graphics Capture delivers frames
+ *   in the monitor's current orientation, so a rotated monitor simply
+ *   produces taller frames.
+ * - The pane is measured in egui points. The result is snapped to whole
+ *   physical pixels for the current scale factor, which changes when the
+ *   window moves between monitors with different DPI.
+ * =============================================================================
+ */
+
+/// Image rectangle in points, relative to the pane's top-left corner.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct FitRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Largest rectangle with the aspect ratio of `content_w` x `content_h` that
+/// fits in a `pane_w` x `pane_h` point pane, centred. Empty when either side
+/// has no area.
+pub fn letterbox(content_w: u32, content_h: u32, pane_w: f32, pane_h: f32, pixels_per_point: f32) -> FitRect {
+    let valid = |v: f32| v.is_finite() && v > 0.0;
+    if content_w == 0 || content_h == 0 || !valid(pane_w) || !valid(pane_h) {
+        return FitRect::default();
+    }
+    let ppp = if valid(pixels_per_point) { pixels_per_point } else { 1.0 };
+
+    // Work in physical pixels so the edges land on pixel boundaries
+    let (pane_px_w, pane_px_h) = ((pane_w * ppp).floor(), (pane_h * ppp).floor());
+    let (cw, ch) = (content_w as f64, content_h as f64);
+    let scale = (pane_px_w as f64 / cw).min(pane_px_h as f64 / ch);
+    let width_px = (cw * scale).round().min(pane_px_w as f64) as f32;
+    let height_px = (ch * scale).round().min(pane_px_h as f64) as f32;
+
+    let x_px = ((pane_px_w - width_px) / 2.0).floor();
+    let y_px = ((pane_px_h - height_px) / 2.0).floor();
+
+    FitRect {
+        x: x_px / ppp,
+        y: y_px / ppp,
+        width: width_px / ppp,
+        height: height_px / ppp,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> FitRect {
+        FitRect { x, y, width, height }
+    }
+
+    #[test]
+    fn matching_aspect_fills_the_pane() {
+        assert_eq!(letterbox(1920, 1080, 480.0, 270.0, 1.0), rect(0.0, 0.0, 480.0, 270.0));
+    }
+
+    #[test]
+    fn wide_pane_gets_side_bars() {
+        assert_eq!(letterbox(1920, 1080, 500.0, 180.0, 1.0), rect(90.0, 0.0, 320.0, 180.0));
+    }
+
+    #[test]
+    fn ultrawide_gets_top_and_bottom_bars() {
+        assert_eq!(letterbox(3440, 1440, 430.0, 300.0, 1.0), rect(0.0, 60.0, 430.0, 180.0));
+    }
+
+    #[test]
+    fn sixteen_by_ten_keeps_its_shape() {
+        assert_eq!(letterbox(1920, 1200, 480.0, 400.0, 1.0), rect(0.0, 50.0, 480.0, 300.0));
+    }
+
+    #[test]
+    fn portrait_monitor_is_tall() {
+        let r = letterbox(1080, 1920, 500.0, 400.0, 1.0);
+        assert!(r.height > r.width);
+        assert_eq!(r, rect(137.0, 0.0, 225.0, 400.0));
+    }
+
+    #[test]
+    fn snaps_to_physical_pixels_at_fractional_scale() {
+        let r = letterbox(1920, 1080, 333.0, 333.0, 1.5);
+        for v in [r.x, r.y, r.width, r.height] {
+            assert_eq!((v * 1.5).fract(), 0.0, "{} is not on a pixel boundary", v);
+        }
+    }
+
+    #[test]
+    fn same_pane_in_points_is_the_same_shape_at_any_dpi() {
+        let low = letterbox(2560, 1080, 400.0, 300.0, 1.0);
+        let high = letterbox(2560, 1080, 400.0, 300.0, 2.0);
+        assert!((low.width - high.width).abs() <= 1.0);
+        assert!((low.height - high.height).abs() <= 1.0);
+    }
+
+    #[test]
+    fn degenerate_input_is_empty() {
+        assert_eq!(letterbox(0, 1080, 400.0, 300.0, 1.0), FitRect::default());
+        assert_eq!(letterbox(1920, 1080, 0.0, 300.0, 1.0), FitRect::default());
+        assert_eq!(letterbox(1920, 1080, f32::NAN, 300.0, 1.0), FitRect::default());
+        // A bad scale factor falls back to 1:1
+        assert_eq!(letterbox(1920, 1080, 480.0, 270.0, 0.0), rect(0.0, 0.0, 480.0, 270.0));
+    }
+
+    proptest! {
+        #[test]
+        fn always_inside_the_pane_and_centred(
+            cw in 1u32..8000, ch in 1u32..8000,
+            pw in 1.0f32..2000.0, ph in 1.0f32..2000.0,
+            ppp in prop_oneof![Just(1.0f32), Just(1.25), Just(1.5), Just(1.75), Just(2.0)],
+        ) {
+            let r = letterbox(cw, ch, pw, ph, ppp);
+            let px = 1.0 / ppp + 1e-3;
+            prop_assert!(r.x >= 0.0 && r.y >= 0.0);
+            prop_assert!(r.x + r.width <= pw + 1e-3 && r.y + r.height <= ph + 1e-3);
+            // Bars on opposite sides differ by at most a physical pixel plus the unused remainder
+            prop_assert!((r.x - (pw - r.x - r.width)).abs() <= 2.0 * px);
+            prop_assert!((r.y - (ph - r.y - r.height)).abs() <= 2.0 * px);
+            // One dimension touches the pane
+            prop_assert!(pw - r.width <= px || ph - r.height <= px);
+            // Aspect kept to within rounding of a pixel
+            if r.width * ppp >= 16.0 && r.height * ppp >= 16.0 {
+                let want = cw as f32 / ch as f32;
+                let got = r.width / r.height;
+                let tolerance = want * (1.0 / (r.width * ppp) + 1.0 / (r.height * ppp)) * 1.5;
+                prop_assert!((got - want).abs() <= tolerance, "{} vs {}", got, want);
+            }
+        }
+    }
+}
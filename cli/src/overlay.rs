@@ -0,0 +1,150 @@
+/*
+ * =============================================================================
+ * WinHider Overlay - Hidden Window Outlines
+ * =============================================================================
+ *
+ * Filename: overlay.rs
+ * Description: Maps window rectangles on the captured monitor into preview
+ *              coordinates, so the preview can outline where hidden windows
+ *              are, and finds the outline under the mouse.
+ *
+ * Notes:
+ * - Window and monitor rectangles are physical screen pixels. The image
+ *   rectangle is where the letterboxed frame sits in the pane, in points, so
+ *   the mapping follows both the capture's scale and the UI's DPI.
+ * - Parts of a window on another monitor are clipped off; a window with
+ *   nothing on the captured monitor gets no outline.
+ * =============================================================================
+ */
+
+use crate::letterbox::FitRect;
+use crate::verify::ScreenRect;
+
+/// A hidden window's outline in preview coordinates.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Outline {
+    pub hwnd: isize,
+    pub label: String,
+    pub rect: FitRect,
+}
+
+/// `window` in the same coordinates as `image`, which shows all of
+/// `monitor`. Clipped to the image; `None` when no part of the window is on
+/// the monitor or either rectangle is empty.
+pub fn map_rect(window: ScreenRect, monitor: ScreenRect, image: FitRect) -> Option<FitRect> {
+    if monitor.is_empty() || !(image.width > 0.0 && image.height > 0.0) {
+        return None;
+    }
+    let visible = window.intersect(&monitor);
+    if visible.is_empty() {
+        return None;
+    }
+
+    let scale_x = image.width / monitor.width() as f32;
+    let scale_y = image.height / monitor.height() as f32;
+    let x = image.x + (visible.left - monitor.left) as f32 * scale_x;
+    let y = image.y + (visible.top - monitor.top) as f32 * scale_y;
+    Some(FitRect {
+        x,
+        y,
+        width: visible.width() as f32 * scale_x,
+        height: visible.height() as f32 * scale_y,
+    })
+}
+
+/// Index of the outline containing `(x, y)`. `outlines` are in z-order with
+/// the topmost window first, so an overlapped window yields to the one on
+/// top of it.
+pub fn hit_test(outlines: &[Outline], x: f32, y: f32) -> Option<usize> {
+    outlines.iter().position(|o| {
+        x >= o.rect.x && x < o.rect.x + o.rect.width && y >= o.rect.y && y < o.rect.y + o.rect.height
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // Shared with the mosaic tests
+    pub(crate) fn screen(left: i32, top: i32, right: i32, bottom: i32) -> ScreenRect {
+        ScreenRect { left, top, right, bottom }
+    }
+
+    pub(crate) fn fit(x: f32, y: f32, width: f32, height: f32) -> FitRect {
+        FitRect { x, y, width, height }
+    }
+
+    fn outline(hwnd: isize, rect: FitRect) -> Outline {
+        Outline { hwnd, label: format!("window {}", hwnd), rect }
+    }
+
+    const PRIMARY: ScreenRect = ScreenRect { left: 0, top: 0, right: 1920, bottom: 1080 };
+
+    #[test]
+    fn full_monitor_fills_the_image() {
+        let image = fit(10.0, 5.0, 480.0, 270.0);
+        assert_eq!(map_rect(PRIMARY, PRIMARY, image), Some(image));
+    }
+
+    #[test]
+    fn window_is_scaled_and_offset_by_the_letterbox() {
+        // Quarter-size preview placed 20 points from the left edge of the pane
+        let got = map_rect(screen(400, 200, 1200, 800), PRIMARY, fit(20.0, 0.0, 480.0, 270.0));
+        assert_eq!(got, Some(fit(120.0, 50.0, 200.0, 150.0)));
+    }
+
+    #[test]
+    fn secondary_monitor_left_of_the_primary() {
+        let left = screen(-2560, 0, 0, 1440);
+        let got = map_rect(screen(-2560, 0, -1280, 720), left, fit(0.0, 0.0, 256.0, 144.0));
+        assert_eq!(got, Some(fit(0.0, 0.0, 128.0, 72.0)));
+    }
+
+    #[test]
+    fn window_spanning_monitors_is_clipped() {
+        let got = map_rect(screen(1800, 980, 2400, 1400), PRIMARY, fit(0.0, 0.0, 1920.0, 1080.0));
+        assert_eq!(got, Some(fit(1800.0, 980.0, 120.0, 100.0)));
+    }
+
+    #[test]
+    fn window_on_another_monitor_has_no_outline() {
+        assert_eq!(map_rect(screen(1920, 0, 2500, 600), PRIMARY, fit(0.0, 0.0, 480.0, 270.0)), None);
+        assert_eq!(map_rect(screen(-700, -500, -100, -10), PRIMARY, fit(0.0, 0.0, 480.0, 270.0)), None);
+    }
+
+    #[test]
+    fn degenerate_inputs_have_no_outline() {
+        let image = fit(0.0, 0.0, 480.0, 270.0);
+        assert_eq!(map_rect(screen(10, 10, 10, 50), PRIMARY, image), None);
+        assert_eq!(map_rect(PRIMARY, screen(0, 0, 0, 0), image), None);
+        assert_eq!(map_rect(PRIMARY, PRIMARY, FitRect::default()), None);
+        assert_eq!(map_rect(PRIMARY, PRIMARY, fit(0.0, 0.0, f32::NAN, 270.0)), None);
+    }
+
+    #[test]
+    fn non_uniform_scale_follows_each_axis() {
+        // A 16:10 frame squeezed into a 16:9 image still lands on the same relative spot
+        let monitor = screen(0, 0, 1920, 1200);
+        let got = map_rect(screen(960, 600, 1920, 1200), monitor, fit(0.0, 0.0, 320.0, 180.0)).unwrap();
+        assert_eq!(got, fit(160.0, 90.0, 160.0, 90.0));
+    }
+
+    #[test]
+    fn hit_test_prefers_the_topmost_window() {
+        let outlines = [
+            outline(1, fit(50.0, 50.0, 100.0, 100.0)),
+            outline(2, fit(0.0, 0.0, 300.0, 200.0)),
+        ];
+        assert_eq!(hit_test(&outlines, 60.0, 60.0), Some(0));
+        assert_eq!(hit_test(&outlines, 10.0, 10.0), Some(1));
+        assert_eq!(hit_test(&outlines, 400.0, 10.0), None);
+    }
+
+    #[test]
+    fn hit_test_edges_are_half_open() {
+        let outlines = [outline(1, fit(10.0, 10.0, 20.0, 20.0))];
+        assert_eq!(hit_test(&outlines, 10.0, 10.0), Some(0));
+        assert_eq!(hit_test(&outlines, 30.0, 15.0), None);
+        assert_eq!(hit_test(&outlines, 15.0, 30.0), None);
+    }
+}
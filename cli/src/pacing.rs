@@ -0,0 +1,226 @@
+/*
+ * =============================================================================
+ * WinHider Pacing - Preview Frame Rate Budget
+ * =============================================================================
+ *
+ * Filename: pacing.rs
+ * Description: Decides how many preview frames per second are worth
+ *              converting, and drops the rest before they cost any CPU.
+ *
+ * Notes:
+ * - The user sets a maximum. While WinHider is in the background it drops to
+ *   a few frames per second, and to one while minimized, since nobody is
+ *   looking at the preview. A clip being recorded keeps its own rate.
+ * - Graphics Capture delivers frames on the monitor's refresh, so a 30 fps
+ *   budget on a 144 Hz screen sees a frame every 7 ms. The pacer schedules
+ *   from its previous slot rather than the last accepted frame, so refresh
+ *   jitter neither pushes the rate over budget nor drags it under.
+ * - The interval lives in an atomic shared with the capture thread, so
+ *   focus changes apply on the next frame without restarting the session.
+ * =============================================================================
+ */
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+pub const MIN_FPS: u32 = 1;
+pub const MAX_FPS: u32 = 60;
+/// Rate while another application has focus.
+pub const UNFOCUSED_FPS: u32 = 5;
+/// Rate while WinHider is minimized.
+pub const MINIMIZED_FPS: u32 = 1;
+
+/// Frames arriving this much early still count as on time.
+const JITTER: Duration = Duration::from_millis(2);
+
+/// What the user can currently see of the preview.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Activity {
+    Focused,
+    Unfocused,
+    Minimized,
+}
+
+impl Activity {
+    /// From the viewport state egui reports. Unknown counts as focused so a
+    /// platform that does not report focus keeps the full rate.
+    pub fn from_viewport(focused: Option<bool>, minimized: Option<bool>) -> Self {
+        if minimized == Some(true) {
+            Activity::Minimized
+        } else if focused == Some(false) {
+            Activity::Unfocused
+        } else {
+            Activity::Focused
+        }
+    }
+}
+
+pub fn clamp_fps(fps: u32) -> u32 {
+    fps.clamp(MIN_FPS, MAX_FPS)
+}
+
+/// Time between frames at `fps`.
+pub fn interval(fps: u32) -> Duration {
+    Duration::from_secs(1) / clamp_fps(fps)
+}
+
+/// Frame rate the preview should run at right now. `recording_fps` is the
+/// rate of a clip in progress, which is kept even in the background.
+pub fn target_fps(max_fps: u32, activity: Activity, recording_fps: Option<u32>) -> u32 {
+    let max_fps = clamp_fps(max_fps);
+    let adaptive = match activity {
+        Activity::Focused => max_fps,
+        Activity::Unfocused => max_fps.min(UNFOCUSED_FPS),
+        Activity::Minimized => max_fps.min(MINIMIZED_FPS),
+    };
+    recording_fps.map_or(adaptive, |fps| adaptive.max(clamp_fps(fps)))
+}
+
+/// Minimum update interval handed to Graphics Capture when a session starts.
+/// Never below `clip_max_fps`, so a recording started later gets its frames
+/// without restarting the session; the pacer enforces the lower preview rate.
+pub fn session_interval(max_fps: u32, clip_max_fps: u32) -> Duration {
+    interval(max_fps.max(clip_max_fps))
+}
+
+/// Frame interval shared between the UI thread and the capture thread.
+/// Zero accepts every frame.
+#[derive(Default, Debug)]
+pub struct SharedInterval(AtomicU64);
+
+impl SharedInterval {
+    pub fn set(&self, interval: Duration) {
+        self.0.store(interval.as_nanos().min(u64::MAX as u128) as u64, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> Duration {
+        Duration::from_nanos(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// Drops frames that arrive before their slot.
+#[derive(Default, Debug)]
+pub struct Pacer {
+    next: Option<Instant>,
+}
+
+impl Pacer {
+    /// Whether a frame arriving at `now` should be kept at `interval`.
+    pub fn accept(&mut self, now: Instant, interval: Duration) -> bool {
+        if self.next.is_some_and(|next| now + JITTER < next) {
+            return false;
+        }
+        // Continue from the previous slot unless the stream stalled, so a
+        // long gap does not turn into a burst of catch-up frames
+        let base = match self.next {
+            Some(next) if now.saturating_duration_since(next) < interval => next,
+            _ => now,
+        };
+        self.next = Some(base + interval);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frames accepted out of a stream at `refresh_hz` over one second.
+    fn accepted_per_second(refresh_hz: u32, fps: u32) -> usize {
+        let start = Instant::now();
+        let mut pacer = Pacer::default();
+        let period = Duration::from_secs(1) / refresh_hz;
+        (0..refresh_hz)
+            .filter(|&i| pacer.accept(start + period * i, interval(fps)))
+            .count()
+    }
+
+    #[test]
+    fn limits_a_fast_monitor_to_the_budget() {
+        for hz in [60, 75, 120, 144, 165, 240] {
+            let got = accepted_per_second(hz, 30);
+            assert!((28..=30).contains(&got), "{} Hz gave {} fps", hz, got);
+        }
+    }
+
+    #[test]
+    fn refresh_jitter_does_not_halve_the_rate() {
+        // 60 Hz with 30 fps: every other frame, even when frames run early
+        let start = Instant::now();
+        let mut pacer = Pacer::default();
+        let mut kept = 0;
+        for i in 0..60u32 {
+            let jitter = if i % 3 == 0 { Duration::from_micros(900) } else { Duration::ZERO };
+            let at = start + Duration::from_secs(1) / 60 * i - jitter.min(Duration::from_secs(1) / 60 * i);
+            if pacer.accept(at, interval(30)) {
+                kept += 1;
+            }
+        }
+        assert!(kept >= 29, "kept {}", kept);
+    }
+
+    #[test]
+    fn slower_source_is_passed_through() {
+        assert_eq!(accepted_per_second(10, 30), 10);
+    }
+
+    #[test]
+    fn zero_interval_accepts_everything() {
+        let start = Instant::now();
+        let mut pacer = Pacer::default();
+        assert!((0..100).all(|i| pacer.accept(start + Duration::from_micros(i), Duration::ZERO)));
+    }
+
+    #[test]
+    fn stall_does_not_cause_a_burst() {
+        let start = Instant::now();
+        let mut pacer = Pacer::default();
+        let step = interval(10);
+        assert!(pacer.accept(start, step));
+        // Nothing for five seconds, then frames at 60 Hz
+        let resume = start + Duration::from_secs(5);
+        let kept = (0..60u32)
+            .filter(|&i| pacer.accept(resume + Duration::from_secs(1) / 60 * i, step))
+            .count();
+        assert!(kept <= 10, "kept {}", kept);
+    }
+
+    #[test]
+    fn background_lowers_the_rate() {
+        assert_eq!(target_fps(30, Activity::Focused, None), 30);
+        assert_eq!(target_fps(30, Activity::Unfocused, None), UNFOCUSED_FPS);
+        assert_eq!(target_fps(30, Activity::Minimized, None), MINIMIZED_FPS);
+        // A low maximum is never raised
+        assert_eq!(target_fps(2, Activity::Unfocused, None), 2);
+    }
+
+    #[test]
+    fn recording_keeps_its_rate_in_the_background() {
+        assert_eq!(target_fps(30, Activity::Minimized, Some(15)), 15);
+        assert_eq!(target_fps(5, Activity::Focused, Some(20)), 20);
+        assert_eq!(target_fps(30, Activity::Focused, Some(10)), 30);
+    }
+
+    #[test]
+    fn fps_is_clamped() {
+        assert_eq!(interval(0), Duration::from_secs(1));
+        assert_eq!(interval(1000), Duration::from_secs(1) / MAX_FPS);
+        assert_eq!(session_interval(10, 30), interval(30));
+    }
+
+    #[test]
+    fn viewport_state_maps_to_activity() {
+        assert_eq!(Activity::from_viewport(Some(true), Some(false)), Activity::Focused);
+        assert_eq!(Activity::from_viewport(Some(false), Some(false)), Activity::Unfocused);
+        assert_eq!(Activity::from_viewport(Some(false), Some(true)), Activity::Minimized);
+        assert_eq!(Activity::from_viewport(None, None), Activity::Focused);
+    }
+
+    #[test]
+    fn shared_interval_round_trips() {
+        let shared = SharedInterval::default();
+        assert_eq!(shared.get(), Duration::ZERO);
+        shared.set(interval(25));
+        assert_eq!(shared.get(), interval(25));
+    }
+}
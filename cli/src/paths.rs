@@ -0,0 +1,214 @@
+/*
+ * =============================================================================
+ * WinHider Paths - Unicode & Long Path Helpers
+ * =============================================================================
+ *
+ * Filename: paths.rs
+ * Description: Converts filesystem paths to the NUL-terminated UTF-16 form the
+ *              W-suffixed Win32 APIs expect, without a lossy trip through
+ *              `&str`. User profiles with accented or CJK names and paths that
+ *              are not valid Unicode survive unchanged.
+ *
+ * Long Paths:
+ * - `std::fs` already handles paths beyond MAX_PATH on its own.
+ * - Raw Win32 calls (and LoadLibraryW inside a target process that is not
+ *   long-path aware) need either the 8.3 short form or the `\\?\` prefix.
+ * =============================================================================
+ */
+
+use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
+
+use windows::core::PCWSTR;
+use windows::Win32::Storage::FileSystem::GetShortPathNameW;
+
+/// MAX_PATH including the terminating NUL.
+pub const MAX_PATH_LEN: usize = 260;
+
+const VERBATIM_PREFIX: &[u16] = &[b'\\' as u16, b'\\' as u16, b'?' as u16, b'\\' as u16];
+const DEVICE_PREFIX: &[u16] = &[b'\\' as u16, b'\\' as u16, b'.' as u16, b'\\' as u16];
+const UNC_MARKER: &[u16] = &[b'U' as u16, b'N' as u16, b'C' as u16, b'\\' as u16];
+
+/// UTF-16 form of `path` without a terminating NUL. Ill-formed UTF-16
+/// (non-Unicode `OsStr`) is passed through untouched.
+pub fn to_wide(path: &Path) -> Vec<u16> {
+    path.as_os_str().encode_wide().collect()
+}
+
+/// NUL-terminated UTF-16 form of `path`.
+pub fn to_wide_nul(path: &Path) -> Vec<u16> {
+    let mut wide = to_wide(path);
+    wide.push(0);
+    wide
+}
+
+/// True when a wide path (without NUL) no longer fits in MAX_PATH.
+pub fn exceeds_max_path(wide: &[u16]) -> bool {
+    wide.len() + 1 > MAX_PATH_LEN
+}
+
+/// Adds the `\\?\` prefix to an absolute wide path (without NUL). UNC paths
+/// become `\\?\UNC\server\share`. Relative, device and already prefixed paths
+/// are returned unchanged because the prefix would change their meaning.
+pub fn with_long_path_prefix(wide: &[u16]) -> Vec<u16> {
+    if wide.starts_with(VERBATIM_PREFIX) || wide.starts_with(DEVICE_PREFIX) {
+        return wide.to_vec();
+    }
+
+    let is_sep = |c: u16| c == b'\\' as u16 || c == b'/' as u16;
+
+    // \\server\share\...
+    if wide.len() > 2 && is_sep(wide[0]) && is_sep(wide[1]) {
+        let mut out = Vec::with_capacity(wide.len() + 6);
+        out.extend_from_slice(VERBATIM_PREFIX);
+        out.extend_from_slice(UNC_MARKER);
+        out.extend(wide[2..].iter().map(|&c| if c == b'/' as u16 { b'\\' as u16 } else { c }));
+        return out;
+    }
+
+    // C:\...
+    let is_drive_absolute = wide.len() >= 3
+        && wide[0] < 0x80
+        && (wide[0] as u8).is_ascii_alphabetic()
+        && wide[1] == b':' as u16
+        && is_sep(wide[2]);
+    if is_drive_absolute {
+        let mut out = Vec::with_capacity(wide.len() + 4);
+        out.extend_from_slice(VERBATIM_PREFIX);
+        // Verbatim paths are not normalised, so forward slashes must go
+        out.extend(wide.iter().map(|&c| if c == b'/' as u16 { b'\\' as u16 } else { c }));
+        return out;
+    }
+
+    wide.to_vec()
+}
+
+/// NUL-terminated path for `LoadLibraryW` in a target process. Short paths
+/// are used as-is; long ones are shortened to their 8.3 form when the volume
+/// has short names, otherwise they get the `\\?\` prefix.
+pub fn loader_path(path: &Path) -> Vec<u16> {
+    let wide = to_wide(path);
+    if !exceeds_max_path(&wide) {
+        return to_wide_nul(path);
+    }
+
+    let mut prefixed = with_long_path_prefix(&wide);
+    prefixed.push(0);
+
+    if let Some(mut short) = short_path(&prefixed) {
+        // GetShortPathNameW keeps the prefix it was given
+        if short.starts_with(VERBATIM_PREFIX) && !short.starts_with(&[VERBATIM_PREFIX, UNC_MARKER].concat()) {
+            short.drain(..VERBATIM_PREFIX.len());
+        }
+        if !exceeds_max_path(&short) {
+            short.push(0);
+            return short;
+        }
+    }
+
+    prefixed
+}
+
+/// Lossy, human readable form of `path` for status messages.
+pub fn display(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn short_path(wide_nul: &[u16]) -> Option<Vec<u16>> {
+    unsafe {
+        let needed = GetShortPathNameW(PCWSTR(wide_nul.as_ptr()), None);
+        if needed == 0 {
+            return None;
+        }
+        let mut buffer = vec![0u16; needed as usize];
+        let len = GetShortPathNameW(PCWSTR(wide_nul.as_ptr()), Some(&mut buffer));
+        if len == 0 || len as usize >= buffer.len() {
+            return None;
+        }
+        buffer.truncate(len as usize);
+        Some(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsString;
+    use std::os::windows::ffi::OsStringExt;
+    use std::path::PathBuf;
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().collect()
+    }
+
+    fn narrow(w: &[u16]) -> String {
+        String::from_utf16(w).unwrap()
+    }
+
+    #[test]
+    fn to_wide_nul_keeps_non_ascii_profile_names() {
+        let path = Path::new(r"C:\Users\Zoë Šťastná\AppData\Local\李小龙\payload.dll");
+        let w = to_wide_nul(path);
+        assert_eq!(w.last(), Some(&0));
+        assert_eq!(narrow(&w[..w.len() - 1]), path.to_str().unwrap());
+    }
+
+    #[test]
+    fn to_wide_passes_ill_formed_utf16_through() {
+        // A lone surrogate cannot be represented as &str
+        let raw = [b'C' as u16, b':' as u16, b'\\' as u16, 0xD800, b'x' as u16];
+        let path = PathBuf::from(OsString::from_wide(&raw));
+        assert!(path.to_str().is_none());
+        assert_eq!(to_wide(&path), raw);
+        assert_eq!(to_wide_nul(&path), [&raw[..], &[0]].concat());
+    }
+
+    #[test]
+    fn exceeds_max_path_counts_the_terminator() {
+        assert!(!exceeds_max_path(&vec![b'a' as u16; MAX_PATH_LEN - 1]));
+        assert!(exceeds_max_path(&vec![b'a' as u16; MAX_PATH_LEN]));
+        assert!(!exceeds_max_path(&[]));
+    }
+
+    #[test]
+    fn prefix_drive_absolute_path() {
+        assert_eq!(narrow(&with_long_path_prefix(&wide(r"C:\a\b.dll"))), r"\\?\C:\a\b.dll");
+        assert_eq!(narrow(&with_long_path_prefix(&wide("d:/a/b.dll"))), r"\\?\d:\a\b.dll");
+    }
+
+    #[test]
+    fn prefix_unc_path() {
+        assert_eq!(
+            narrow(&with_long_path_prefix(&wide(r"\\server\share\dir\x.dll"))),
+            r"\\?\UNC\server\share\dir\x.dll"
+        );
+    }
+
+    #[test]
+    fn prefix_leaves_other_forms_alone() {
+        for p in [r"\\?\C:\a", r"\\?\UNC\s\x", r"\\.\pipe\x", r"relative\x.dll", r"C:relative", r"\rooted", ""] {
+            assert_eq!(narrow(&with_long_path_prefix(&wide(p))), p, "{}", p);
+        }
+    }
+
+    #[test]
+    fn prefix_keeps_non_ascii_segments() {
+        let p = r"C:\Users\Jürgen\AppData\Local\東京\x.dll";
+        assert_eq!(narrow(&with_long_path_prefix(&wide(p))), format!(r"\\?\{}", p));
+    }
+
+    #[test]
+    fn loader_path_short_paths_are_unchanged() {
+        let path = Path::new(r"C:\Users\Zoë\AppData\Local\x.dll");
+        assert_eq!(loader_path(path), to_wide_nul(path));
+    }
+
+    #[test]
+    fn loader_path_long_missing_file_gets_prefix() {
+        // GetShortPathNameW fails for files that do not exist, leaving the prefix form
+        let long = format!(r"C:\does-not-exist\{}\x.dll", "ü".repeat(300));
+        let w = loader_path(Path::new(&long));
+        assert_eq!(w.last(), Some(&0));
+        assert_eq!(narrow(&w[..w.len() - 1]), format!(r"\\?\{}", long));
+    }
+}
@@ -0,0 +1,239 @@
+/*
+ * =============================================================================
+ * WinHider Planner - Injection Batching & Strategy
+ * =============================================================================
+ *
+ * Filename: planner.rs
+ * Description: Turns a list of per-window requests into the smallest set of
+ *              injections. The payload always acts on every window of the
+ *              process it is loaded into, so requests are grouped by PID and
+ *              each process gets exactly one injection carrying the combined
+ *              action set.
+ *
+ * Rules:
+ * - Processes keep the order in which they first appear.
+ * - Repeated actions collapse into one.
+ * - Capture and taskbar are independent; within each, the last request wins
+ *   (a hide followed by a show for the same process ends up as a show).
+ * - That merge is only for the payload, which acts on the whole process.
+ *   External calls act on one window at a time, so each window gets its own
+ *   last request (`window_actions`).
+ *
+ * Strategies:
+ * - Display affinity can only be changed by the process owning the window,
+ *   so capture actions on other processes always need the injection.
+ * - Taskbar styles can be changed from outside; with "prefer non-injecting"
+ *   enabled they use an external call and only fall back to injection if
+ *   that call fails.
+ * - Windows of WinHider itself never need an injection.
+ * =============================================================================
+ */
+
+use windows::Win32::Foundation::HWND;
+
+use crate::InjectionAction;
+
+/// One requested action for one window.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct InjectionTask {
+    pub hwnd: HWND,
+    pub pid: u32,
+    pub action: InjectionAction,
+}
+
+/// A single injection into one process.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PlannedInjection {
+    pub pid: u32,
+    /// Combined actions: at most one capture and one taskbar action.
+    pub actions: Vec<InjectionAction>,
+    /// Requests folded into this injection, in submission order.
+    pub tasks: Vec<InjectionTask>,
+}
+
+/// How an action is carried out.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    /// Win32 call made from WinHider, no code runs in the target.
+    External,
+    /// Payload DLL loaded into the target process.
+    Injection,
+}
+
+impl Strategy {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::External => "external call",
+            Self::Injection => "injection",
+        }
+    }
+}
+
+/// Least invasive strategy for `action`. `own_process` is true when the
+/// target is WinHider itself.
+pub fn choose_strategy(action: InjectionAction, own_process: bool, prefer_external: bool) -> Strategy {
+    if own_process {
+        return Strategy::External;
+    }
+    match action {
+        InjectionAction::HideCapture | InjectionAction::ShowCapture => Strategy::Injection,
+        InjectionAction::HideTaskbar | InjectionAction::ShowTaskbar if prefer_external => Strategy::External,
+        InjectionAction::HideTaskbar | InjectionAction::ShowTaskbar => Strategy::Injection,
+    }
+}
+
+/// Whether `a` and `b` change the same thing, capture or taskbar.
+pub fn same_dimension(a: InjectionAction, b: InjectionAction) -> bool {
+    let is_capture = |a| matches!(a, InjectionAction::HideCapture | InjectionAction::ShowCapture);
+    is_capture(a) == is_capture(b)
+}
+
+/// The last action of the same dimension as `action` that each window in
+/// `tasks` asked for, in the order the windows first appear.
+pub fn window_actions(tasks: &[InjectionTask], action: InjectionAction) -> Vec<(HWND, InjectionAction)> {
+    let mut windows: Vec<(HWND, InjectionAction)> = Vec::new();
+    for task in tasks.iter().filter(|t| same_dimension(t.action, action)) {
+        match windows.iter_mut().find(|(hwnd, _)| *hwnd == task.hwnd) {
+            Some(window) => window.1 = task.action,
+            None => windows.push((task.hwnd, task.action)),
+        }
+    }
+    windows
+}
+
+pub fn plan(tasks: &[InjectionTask]) -> Vec<PlannedInjection> {
+    let mut planned: Vec<PlannedInjection> = Vec::new();
+
+    for task in tasks {
+        match planned.iter_mut().find(|p| p.pid == task.pid) {
+            Some(injection) => injection.tasks.push(*task),
+            None => planned.push(PlannedInjection { pid: task.pid, actions: Vec::new(), tasks: vec![*task] }),
+        }
+    }
+
+    for injection in &mut planned {
+        let mut capture = None;
+        let mut taskbar = None;
+        for task in &injection.tasks {
+            match task.action {
+                InjectionAction::HideCapture | InjectionAction::ShowCapture => capture = Some(task.action),
+                InjectionAction::HideTaskbar | InjectionAction::ShowTaskbar => taskbar = Some(task.action),
+            }
+        }
+        injection.actions = capture.into_iter().chain(taskbar).collect();
+    }
+
+    planned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use InjectionAction::*;
+
+    fn task(hwnd: isize, pid: u32, action: InjectionAction) -> InjectionTask {
+        InjectionTask { hwnd: HWND(hwnd), pid, action }
+    }
+
+    #[test]
+    fn empty_input_plans_nothing() {
+        assert!(plan(&[]).is_empty());
+    }
+
+    #[test]
+    fn auto_hide_pair_becomes_one_injection() {
+        let tasks = [task(1, 10, HideTaskbar), task(1, 10, HideCapture)];
+        let planned = plan(&tasks);
+        assert_eq!(planned.len(), 1);
+        assert_eq!(planned[0].pid, 10);
+        assert_eq!(planned[0].actions, vec![HideCapture, HideTaskbar]);
+        assert_eq!(planned[0].tasks, tasks.to_vec());
+    }
+
+    #[test]
+    fn same_action_on_many_windows_of_one_process_is_deduplicated() {
+        let tasks: Vec<_> = (1..=5).map(|h| task(h, 42, HideCapture)).collect();
+        let planned = plan(&tasks);
+        assert_eq!(planned.len(), 1);
+        assert_eq!(planned[0].actions, vec![HideCapture]);
+        assert_eq!(planned[0].tasks.len(), 5);
+    }
+
+    #[test]
+    fn processes_keep_first_seen_order() {
+        let tasks = [
+            task(1, 30, HideCapture),
+            task(2, 10, HideCapture),
+            task(3, 30, HideTaskbar),
+            task(4, 20, ShowCapture),
+        ];
+        let pids: Vec<u32> = plan(&tasks).iter().map(|p| p.pid).collect();
+        assert_eq!(pids, vec![30, 10, 20]);
+    }
+
+    #[test]
+    fn last_request_wins_per_dimension() {
+        let tasks = [
+            task(1, 7, HideCapture),
+            task(2, 7, ShowTaskbar),
+            task(1, 7, ShowCapture),
+            task(2, 7, HideTaskbar),
+        ];
+        let planned = plan(&tasks);
+        assert_eq!(planned[0].actions, vec![ShowCapture, HideTaskbar]);
+    }
+
+    #[test]
+    fn capture_on_other_processes_always_injects() {
+        for action in [HideCapture, ShowCapture] {
+            assert_eq!(choose_strategy(action, false, true), Strategy::Injection);
+            assert_eq!(choose_strategy(action, false, false), Strategy::Injection);
+        }
+    }
+
+    #[test]
+    fn taskbar_follows_preference() {
+        for action in [HideTaskbar, ShowTaskbar] {
+            assert_eq!(choose_strategy(action, false, true), Strategy::External);
+            assert_eq!(choose_strategy(action, false, false), Strategy::Injection);
+        }
+    }
+
+    #[test]
+    fn own_process_never_injects() {
+        for action in [HideCapture, ShowCapture, HideTaskbar, ShowTaskbar] {
+            assert_eq!(choose_strategy(action, true, false), Strategy::External);
+        }
+    }
+
+    #[test]
+    fn dimensions_are_independent() {
+        let planned = plan(&[task(1, 7, HideCapture), task(1, 7, ShowTaskbar)]);
+        assert_eq!(planned.len(), 1);
+        assert_eq!(planned[0].actions, vec![HideCapture, ShowTaskbar]);
+
+        // Within one dimension the last request wins, leaving the other alone
+        let planned = plan(&[task(1, 7, HideCapture), task(1, 7, ShowTaskbar), task(2, 7, ShowCapture)]);
+        assert_eq!(planned.len(), 1);
+        assert_eq!(planned[0].actions, vec![ShowCapture, ShowTaskbar]);
+    }
+
+    #[test]
+    fn each_window_keeps_its_own_last_action() {
+        let tasks = [
+            task(1, 7, HideTaskbar),
+            task(2, 7, ShowTaskbar),
+            task(1, 7, HideCapture),
+            task(3, 7, ShowTaskbar),
+            task(3, 7, HideTaskbar),
+        ];
+        // The payload gets one merged action for the process
+        assert_eq!(plan(&tasks)[0].actions, vec![HideCapture, HideTaskbar]);
+        assert_eq!(
+            window_actions(&tasks, ShowTaskbar),
+            vec![(HWND(1), HideTaskbar), (HWND(2), ShowTaskbar), (HWND(3), HideTaskbar)]
+        );
+        assert_eq!(window_actions(&tasks, ShowCapture), vec![(HWND(1), HideCapture)]);
+    }
+}
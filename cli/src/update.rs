@@ -0,0 +1,756 @@
+/*
+ * =============================================================================
+ * WinHider Update - Release Channels and Sources
+ * =============================================================================
+ *
+ * Filename: update.rs
+ * Description: Asks the configured update source for its releases and picks
+ *              the one the update checker offers on the user's channel.
+ *
+ * Notes:
+ * - The source is GitHub (github.com or a GitHub Enterprise server, by API
+ *   URL) or a plain JSON manifest, such as an internal mirror. A manifest is
+ *   a list of releases in the same shape as GitHub's release list.
+ * - Stable offers full releases only. Beta also offers releases marked as
+ *   pre-releases, so beta testers get the newest build of either kind.
+ * - Drafts are never offered; only maintainers can see them anyway.
+ * - The newest release is chosen by version, not by publication date, so a
+ *   hotfix for an older line published last does not win. Tags that are not
+ *   versions are skipped.
+ * - The release list rather than GitHub's `latest` release is read for both
+ *   channels, so an update also brings the notes of every release skipped
+ *   since the running version.
+ * - The last response is cached with its ETag. Automatic checks within
+ *   `MIN_CHECK_INTERVAL` answer from the cache without a request, and other
+ *   checks ask conditionally, so an unchanged release list costs GitHub's
+ *   rate limit nothing. Once rate limited, no request is made until the time
+ *   the server gave, and the error says when that is.
+ * =============================================================================
+ */
+
+use std::io::Read;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::proxy::Proxy;
+use crate::semver::Version;
+
+pub const GITHUB_API: &str = "https://api.github.com";
+
+/// Releases beyond this many are too old to be offered or to show notes for.
+const LIST_PAGE_SIZE: u32 = 30;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Automatic checks closer together than this use the cached response.
+pub const MIN_CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Wait after a rate-limit response that does not say how long to wait.
+const DEFAULT_RETRY_SECS: i64 = 60;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+impl Channel {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Stable => "Stable",
+            Self::Beta => "Beta",
+        }
+    }
+
+    /// Whether `release` may be offered on this channel.
+    pub fn accepts(&self, release: &Release) -> bool {
+        !release.draft && (!release.prerelease || *self == Self::Beta)
+    }
+}
+
+/// Where the update checker looks for releases.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    GitHub {
+        owner: String,
+        repo: String,
+        /// REST API root; `https://<host>/api/v3` for GitHub Enterprise.
+        #[serde(default = "default_github_api")]
+        api_url: String,
+    },
+    Manifest {
+        url: String,
+    },
+}
+
+fn default_github_api() -> String {
+    GITHUB_API.to_string()
+}
+
+impl Source {
+    pub fn github(owner: &str, repo: &str) -> Self {
+        Source::GitHub { owner: owner.to_string(), repo: repo.to_string(), api_url: default_github_api() }
+    }
+
+    /// URL listing the releases.
+    pub fn releases_url(&self) -> String {
+        match self {
+            Source::GitHub { owner, repo, api_url } => {
+                format!("{}/repos/{}/{}/releases?per_page={}", api_url.trim_end_matches('/'), owner, repo, LIST_PAGE_SIZE)
+            }
+            Source::Manifest { url } => url.clone(),
+        }
+    }
+
+    /// Short description for the update dialog.
+    pub fn describe(&self) -> String {
+        match self {
+            Source::GitHub { owner, repo, api_url } if api_url.trim_end_matches('/') == GITHUB_API => {
+                format!("GitHub {}/{}", owner, repo)
+            }
+            Source::GitHub { owner, repo, api_url } => format!("{}/{} at {}", owner, repo, api_url),
+            Source::Manifest { url } => url.clone(),
+        }
+    }
+
+}
+
+/// Releases in a response from `Source::releases_url`.
+pub fn parse_releases(body: &str) -> Result<Vec<Release>, String> {
+    serde_json::from_str(body).map_err(|e| format!("Invalid response format: {}", e))
+}
+
+/// A downloadable file attached to a release.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+/// The fields of a GitHub release the updater needs.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Release notes, in Markdown.
+    #[serde(default)]
+    pub body: Option<String>,
+    /// RFC 3339 time, e.g. `2024-05-01T12:00:00Z`.
+    #[serde(default)]
+    pub published_at: Option<String>,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
+    /// Release page.
+    #[serde(default)]
+    pub html_url: Option<String>,
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+}
+
+impl Release {
+    /// Release name, or the tag when it has none.
+    pub fn title(&self) -> &str {
+        self.name.as_deref().map(str::trim).filter(|n| !n.is_empty()).unwrap_or(&self.tag_name)
+    }
+
+    pub fn asset(&self, name: &str) -> Option<&Asset> {
+        self.assets.iter().find(|a| a.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Download URL of the asset called `name`.
+    pub fn asset_url(&self, name: &str) -> Option<&str> {
+        self.asset(name).map(|a| a.browser_download_url.as_str())
+    }
+}
+
+/// A newer release and what changed on the way to it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Update {
+    pub latest: Release,
+    /// Every release after the running version up to `latest`, newest first.
+    pub changes: Vec<Release>,
+}
+
+/// Result of an update check.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Check {
+    UpToDate,
+    Available(Update),
+}
+
+/// A complete response.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Response {
+    /// Value of header `name`, in any case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The body of a successful response; any other status is an error.
+    pub fn into_body(self) -> Result<String, String> {
+        match self.status {
+            200..=299 => Ok(self.body),
+            407 => Err("The proxy rejected the credentials (HTTP 407)".to_string()),
+            status => Err(format!("The update server returned HTTP {}", status)),
+        }
+    }
+
+    /// Unix time before which a rate-limited server should not be asked
+    /// again, or `None` if this is not a rate-limit response. GitHub answers
+    /// 403 or 429 with `Retry-After`, or with `X-RateLimit-Remaining: 0` and
+    /// the reset time.
+    pub fn rate_limited_until(&self, now: i64) -> Option<i64> {
+        let number = |name: &str| self.header(name).and_then(|v| v.trim().parse::<i64>().ok());
+        let retry_after = number("Retry-After").map(|secs| now + secs.max(0));
+        let exhausted = number("X-RateLimit-Remaining") == Some(0);
+        match self.status {
+            429 => Some(retry_after.or(number("X-RateLimit-Reset")).unwrap_or(now + DEFAULT_RETRY_SECS)),
+            403 if retry_after.is_some() => retry_after,
+            403 if exhausted => Some(number("X-RateLimit-Reset").unwrap_or(now + DEFAULT_RETRY_SECS)),
+            _ => None,
+        }
+    }
+}
+
+/// What is kept between update checks.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct Cache {
+    /// Unix time of the last check that reached the server.
+    #[serde(default)]
+    pub checked_at: Option<i64>,
+    /// URL the ETag and body belong to.
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    /// Unix time before which the server asked not to be asked again.
+    #[serde(default)]
+    pub retry_at: Option<i64>,
+}
+
+/// Error for a check refused until `retry_at`.
+fn rate_limit_error(retry_at: i64, now: i64) -> String {
+    let secs = (retry_at - now).max(1);
+    let wait = if secs < 90 {
+        format!("{} seconds", secs)
+    } else if secs < 90 * 60 {
+        format!("{} minutes", (secs + 59) / 60)
+    } else {
+        format!("{} hours", (secs + 3599) / 3600)
+    };
+    format!("The update server's rate limit was reached. Try again in {}.", wait)
+}
+
+/// A response body being received.
+pub struct Stream {
+    pub status: u16,
+    /// `Content-Length`, when the server sent one.
+    pub length: Option<u64>,
+    pub reader: Box<dyn Read + Send>,
+}
+
+/// The HTTP requests the update checker makes.
+pub trait HttpClient {
+    /// GET of `url` with extra request `headers`. Any status is a response;
+    /// only failing to get one is an error.
+    fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<Response, String>;
+
+    /// GET of `url` from byte `offset` on. A server that supports ranges
+    /// answers 206, one that does not answers 200 with the whole body, and
+    /// 416 means there is nothing past `offset`. Other statuses are errors.
+    fn get_from(&self, url: &str, offset: u64) -> Result<Stream, String>;
+}
+
+/// `HttpClient` over `ureq`.
+pub struct UreqClient {
+    agent: ureq::Agent,
+    /// Agent that goes through the proxy, and the proxy.
+    proxied: Option<(ureq::Agent, Proxy)>,
+    user_agent: String,
+}
+
+fn agent_builder() -> ureq::AgentBuilder {
+    // No overall timeout, which would cut off a slow installer download
+    ureq::AgentBuilder::new().timeout_connect(TIMEOUT).timeout_read(TIMEOUT)
+}
+
+impl UreqClient {
+    /// A client that connects directly.
+    pub fn new(user_agent: &str) -> Self {
+        UreqClient { agent: agent_builder().build(), proxied: None, user_agent: user_agent.to_string() }
+    }
+
+    /// A client that sends requests through `proxy` unless its bypass list
+    /// says otherwise.
+    pub fn with_proxy(user_agent: &str, proxy: Option<Proxy>) -> Result<Self, String> {
+        let proxied = match proxy {
+            Some(proxy) => {
+                let address = ureq::Proxy::new(proxy.ureq_address()).map_err(|e| format!("Invalid proxy: {}", e))?;
+                Some((agent_builder().proxy(address).build(), proxy))
+            }
+            None => None,
+        };
+        Ok(UreqClient { proxied, ..UreqClient::new(user_agent) })
+    }
+
+    /// A GET of `url` on the agent it goes through.
+    fn request(&self, url: &str) -> ureq::Request {
+        let request = match self.proxied.as_ref().filter(|(_, proxy)| !proxy.bypasses(url)) {
+            Some((agent, proxy)) => {
+                // ureq authenticates the CONNECT for HTTPS but not plain HTTP requests it forwards
+                let plain = url.get(..7).is_some_and(|scheme| scheme.eq_ignore_ascii_case("http://"));
+                match proxy.authorization().filter(|_| plain) {
+                    Some(authorization) => agent.get(url).set("Proxy-Authorization", &authorization),
+                    None => agent.get(url),
+                }
+            }
+            None => self.agent.get(url),
+        };
+        request.set("User-Agent", &self.user_agent)
+    }
+}
+
+impl HttpClient for UreqClient {
+    fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<Response, String> {
+        let request = headers.iter().fold(self.request(url), |request, (name, value)| request.set(name, value));
+        let response = match request.call() {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(e.to_string()),
+        };
+        let status = response.status();
+        let headers = response.headers_names().into_iter()
+            .filter_map(|name| response.header(&name).map(|value| (name.clone(), value.to_string())))
+            .collect();
+        let body = response.into_string().map_err(|e| format!("Failed to read the response: {}", e))?;
+        Ok(Response { status, headers, body })
+    }
+
+    fn get_from(&self, url: &str, offset: u64) -> Result<Stream, String> {
+        let mut request = self.request(url);
+        if offset > 0 {
+            request = request.set("Range", &format!("bytes={}-", offset));
+        }
+        let response = match request.call() {
+            Ok(response) | Err(ureq::Error::Status(416, response)) => response,
+            Err(ureq::Error::Status(407, _)) => return Err("The proxy rejected the credentials (HTTP 407)".to_string()),
+            Err(ureq::Error::Status(code, _)) => return Err(format!("The update server returned HTTP {}", code)),
+            Err(e) => return Err(e.to_string()),
+        };
+        Ok(Stream {
+            status: response.status(),
+            length: response.header("Content-Length").and_then(|v| v.parse().ok()),
+            reader: Box::new(response.into_reader()),
+        })
+    }
+}
+
+/// The releases `source` lists, updating `cache`. `now` is the Unix time.
+/// Unless `forced` by the user, a request within `MIN_CHECK_INTERVAL` of the
+/// last one is answered from the cache.
+pub fn fetch_releases(
+    client: &dyn HttpClient,
+    source: &Source,
+    cache: &mut Cache,
+    now: i64,
+    forced: bool,
+) -> Result<Vec<Release>, String> {
+    let url = source.releases_url();
+    if let Some(retry_at) = cache.retry_at.filter(|&t| t > now) {
+        return Err(rate_limit_error(retry_at, now));
+    }
+
+    let cached = cache.body.clone().filter(|_| cache.url == url);
+    let recent = cache.checked_at.is_some_and(|t| (now - t) < MIN_CHECK_INTERVAL.as_secs() as i64 && t <= now);
+    if let Some(body) = cached.as_ref().filter(|_| recent && !forced) {
+        return parse_releases(body);
+    }
+
+    let etag = cache.etag.clone().filter(|_| cached.is_some());
+    let headers: Vec<(&str, &str)> = etag.iter().map(|etag| ("If-None-Match", etag.as_str())).collect();
+    let response = client.get(&url, &headers)?;
+    if let Some(retry_at) = response.rate_limited_until(now) {
+        cache.retry_at = Some(retry_at);
+        return Err(rate_limit_error(retry_at, now));
+    }
+    cache.retry_at = None;
+
+    let body = match (response.status, cached) {
+        (304, Some(body)) => body,
+        _ => {
+            let etag = response.header("ETag").map(str::to_string);
+            let body = response.into_body()?;
+            cache.url = url;
+            cache.etag = etag;
+            cache.body = Some(body.clone());
+            body
+        }
+    };
+    cache.checked_at = Some(now);
+    parse_releases(&body)
+}
+
+/// Whether `releases` hold one newer than `current` that `channel` accepts.
+pub fn check_releases(releases: &[Release], channel: Channel, current: &str) -> Result<Check, String> {
+    let current = current.parse::<Version>()?;
+    let Some(latest) = newest(releases, channel) else {
+        return Ok(Check::UpToDate);
+    };
+    let latest_version = latest.tag_name.parse::<Version>()?;
+    if latest_version <= current {
+        return Ok(Check::UpToDate);
+    }
+    Ok(Check::Available(Update {
+        latest: latest.clone(),
+        changes: changes(releases, channel, &current, &latest_version),
+    }))
+}
+
+/// Releases after `previous` up to the running `current`, for the "What's
+/// New" dialog after an upgrade.
+pub fn whats_new(releases: &[Release], channel: Channel, previous: &str, current: &str) -> Result<Vec<Release>, String> {
+    let after = previous.parse::<Version>()?;
+    let up_to = current.parse::<Version>()?;
+    // Running a pre-release means its notes are wanted whatever the channel
+    let channel = if up_to.pre.is_empty() { channel } else { Channel::Beta };
+    Ok(changes(releases, channel, &after, &up_to))
+}
+
+/// Releases `channel` accepts that are newer than `after` and no newer than
+/// `up_to`, newest first.
+pub fn changes(releases: &[Release], channel: Channel, after: &Version, up_to: &Version) -> Vec<Release> {
+    let mut found: Vec<(Version, &Release)> = releases.iter()
+        .filter(|r| channel.accepts(r))
+        .filter_map(|r| r.tag_name.parse::<Version>().ok().map(|v| (v, r)))
+        .filter(|(v, _)| v > after && v <= up_to)
+        .collect();
+    found.sort_by(|(a, _), (b, _)| b.cmp(a));
+    found.into_iter().map(|(_, r)| r.clone()).collect()
+}
+
+/// Newest release in `releases` that `channel` accepts.
+pub fn newest(releases: &[Release], channel: Channel) -> Option<&Release> {
+    releases.iter()
+        .filter(|r| channel.accepts(r))
+        .filter_map(|r| r.tag_name.parse::<Version>().ok().map(|v| (v, r)))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, r)| r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Reply, Request, Server};
+    use std::net::TcpListener;
+
+    fn release(tag: &str, prerelease: bool, draft: bool) -> Release {
+        Release { tag_name: tag.to_string(), prerelease, draft, ..Default::default() }
+    }
+
+    /// A JSON reply, as both sources send.
+    fn json(status: u16, body: &str) -> Reply {
+        Reply::new(status, body).header("Content-Type", "application/json")
+    }
+
+    fn lines(seen: Vec<Request>) -> Vec<String> {
+        seen.into_iter().map(|request| request.line).collect()
+    }
+
+    const NOW: i64 = 1_700_000_000;
+
+    /// Asks `source` whether `channel` has a release newer than `current`,
+    /// the way the app does.
+    fn check(
+        client: &dyn HttpClient,
+        source: &Source,
+        channel: Channel,
+        current: &str,
+        cache: &mut Cache,
+        now: i64,
+        forced: bool,
+    ) -> Result<Check, String> {
+        check_releases(&fetch_releases(client, source, cache, now, forced)?, channel, current)
+    }
+
+    /// A check with nothing cached.
+    fn check_once(client: &dyn HttpClient, source: &Source, channel: Channel, current: &str) -> Result<Check, String> {
+        check(client, source, channel, current, &mut Cache::default(), NOW, false)
+    }
+
+    fn github_at(base: &str) -> Source {
+        Source::GitHub { owner: "owner".to_string(), repo: "repo".to_string(), api_url: base.to_string() }
+    }
+
+    const LATEST: &str = r#"[{"tag_name": "v1.2.1", "prerelease": false, "draft": false,
+        "html_url": "https://github.com/owner/repo/releases/tag/v1.2.1",
+        "assets": [{"name": "WinhiderInstaller.exe", "browser_download_url": "https://example.com/WinhiderInstaller.exe"}]}]"#;
+
+    const LIST: &str = r#"[
+        {"tag_name": "v1.3.0-beta.1", "prerelease": true, "draft": false},
+        {"tag_name": "v1.2.1", "prerelease": false, "draft": false}
+    ]"#;
+
+    fn releases() -> Vec<Release> {
+        // Newest first, as GitHub lists them
+        vec![
+            release("v1.3.0-beta.2", false, true),
+            release("v1.3.0-beta.1", true, false),
+            release("v1.2.1", false, false),
+            release("nightly", true, false),
+            release("v1.2.0", false, false),
+        ]
+    }
+
+    #[test]
+    fn stable_skips_pre_releases_and_drafts() {
+        assert_eq!(newest(&releases(), Channel::Stable).map(|r| r.tag_name.as_str()), Some("v1.2.1"));
+    }
+
+    #[test]
+    fn beta_offers_the_newest_pre_release() {
+        assert_eq!(newest(&releases(), Channel::Beta).map(|r| r.tag_name.as_str()), Some("v1.3.0-beta.1"));
+    }
+
+    #[test]
+    fn beta_prefers_the_release_over_its_pre_releases() {
+        let mut list = releases();
+        list.insert(0, release("v1.3.0", false, false));
+        assert_eq!(newest(&list, Channel::Beta).map(|r| r.tag_name.as_str()), Some("v1.3.0"));
+    }
+
+    #[test]
+    fn newest_is_by_version_not_list_order() {
+        // A hotfix for the old line published after the new release
+        let list = vec![release("v1.1.5", false, false), release("v1.2.0", false, false)];
+        assert_eq!(newest(&list, Channel::Stable).map(|r| r.tag_name.as_str()), Some("v1.2.0"));
+    }
+
+    #[test]
+    fn nothing_eligible() {
+        assert_eq!(newest(&[], Channel::Beta), None);
+        let list = vec![release("v2.0.0-rc.1", true, false), release("nightly", false, false)];
+        assert_eq!(newest(&list, Channel::Stable), None);
+    }
+
+    #[test]
+    fn parses_the_github_release_list() {
+        let json = r#"[
+            {"tag_name": "v1.3.0-beta.1", "prerelease": true, "draft": false, "id": 1, "assets": []},
+            {"tag_name": "v1.2.1", "prerelease": false, "draft": false}
+        ]"#;
+        let list = parse_releases(json).unwrap();
+        assert_eq!(list, vec![release("v1.3.0-beta.1", true, false), release("v1.2.1", false, false)]);
+    }
+
+    #[test]
+    fn github_stable_reads_the_release_list() {
+        let server = Server::replying(vec![json(200, LATEST)]);
+        let got = check_once(&UreqClient::new("test"), &github_at(&server.base), Channel::Stable, "v1.2.0").unwrap();
+        let Check::Available(update) = got else { panic!("{:?}", got) };
+        assert_eq!(update.latest.tag_name, "v1.2.1");
+        assert_eq!(update.latest.asset_url("winhiderinstaller.exe"), Some("https://example.com/WinhiderInstaller.exe"));
+        assert_eq!(lines(server.join()), vec!["GET /repos/owner/repo/releases?per_page=30 HTTP/1.1"]);
+    }
+
+    #[test]
+    fn github_beta_reads_the_release_list() {
+        let server = Server::replying(vec![json(200, LIST)]);
+        let got = check_once(&UreqClient::new("test"), &github_at(&server.base), Channel::Beta, "v1.2.1").unwrap();
+        assert!(matches!(got, Check::Available(ref u) if u.latest.tag_name == "v1.3.0-beta.1"), "{:?}", got);
+        assert_eq!(lines(server.join()), vec!["GET /repos/owner/repo/releases?per_page=30 HTTP/1.1"]);
+    }
+
+    #[test]
+    fn manifest_is_read_for_either_channel() {
+        let server = Server::replying(vec![json(200, LIST), json(200, LIST)]);
+        let source = Source::Manifest { url: format!("{}/mirror/winhider.json", server.base) };
+        let client = UreqClient::new("test");
+        assert_eq!(check_once(&client, &source, Channel::Stable, "v1.2.1"), Ok(Check::UpToDate));
+        assert!(matches!(check_once(&client, &source, Channel::Beta, "v1.2.1"), Ok(Check::Available(_))));
+        assert_eq!(lines(server.join()), vec!["GET /mirror/winhider.json HTTP/1.1"; 2]);
+    }
+
+    #[test]
+    fn server_errors_are_reported() {
+        let server = Server::replying(vec![json(404, r#"{"message": "Not Found"}"#), json(200, "<html>mirror down</html>")]);
+        let client = UreqClient::new("test");
+        let source = github_at(&server.base);
+        assert_eq!(check_once(&client, &source, Channel::Stable, "v1.0.0"), Err("The update server returned HTTP 404".to_string()));
+        let err = check_once(&client, &source, Channel::Stable, "v1.0.0").unwrap_err();
+        assert!(err.starts_with("Invalid response format"), "{}", err);
+        server.join();
+    }
+
+    #[test]
+    fn offline_is_an_error() {
+        // Bind and drop to get a port nothing listens on
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let source = github_at(&format!("http://127.0.0.1:{}", port));
+        assert!(check_once(&UreqClient::new("test"), &source, Channel::Stable, "v1.0.0").is_err());
+    }
+
+    #[test]
+    fn update_lists_every_skipped_release() {
+        let list = vec![
+            release("v1.3.0-beta.1", true, false),
+            release("v1.2.1", false, false),
+            release("v1.1.1", false, false),
+            release("v1.2.0", false, false),
+            release("v1.1.0", false, false),
+            release("v1.0.0", false, false),
+        ];
+        let tags = |got: Vec<Release>| got.into_iter().map(|r| r.tag_name).collect::<Vec<_>>();
+        let v = |tag: &str| tag.parse::<Version>().unwrap();
+        assert_eq!(tags(changes(&list, Channel::Stable, &v("1.1.0"), &v("1.2.1"))), vec!["v1.2.1", "v1.2.0", "v1.1.1"]);
+        assert_eq!(tags(changes(&list, Channel::Beta, &v("1.2.1"), &v("1.3.0-beta.1"))), vec!["v1.3.0-beta.1"]);
+        assert_eq!(tags(changes(&list, Channel::Stable, &v("1.2.1"), &v("1.2.1"))), Vec::<String>::new());
+    }
+
+    #[test]
+    fn whats_new_follows_a_pre_release_whatever_the_channel() {
+        let list = vec![release("v1.3.0-beta.1", true, false), release("v1.2.1", false, false), release("v1.2.0", false, false)];
+        let tags = |got: Vec<Release>| got.into_iter().map(|r| r.tag_name).collect::<Vec<_>>();
+        assert_eq!(whats_new(&list, Channel::Stable, "v1.2.0", "v1.2.1").map(tags), Ok(vec!["v1.2.1".to_string()]));
+        assert_eq!(
+            whats_new(&list, Channel::Stable, "v1.2.0", "v1.3.0-beta.1").map(tags),
+            Ok(vec!["v1.3.0-beta.1".to_string(), "v1.2.1".to_string()])
+        );
+    }
+
+    #[test]
+    fn check_reports_the_changes_since_the_running_version() {
+        let server = Server::replying(vec![json(200, r#"[
+            {"tag_name": "v1.2.0", "name": "Preview pacing", "body": "- Frame budget"},
+            {"tag_name": "v1.1.1", "name": "", "body": "Fixes"},
+            {"tag_name": "v1.1.0"}
+        ]"#)]);
+        let got = check_once(&UreqClient::new("test"), &github_at(&server.base), Channel::Stable, "v1.1.0").unwrap();
+        let Check::Available(update) = got else { panic!("{:?}", got) };
+        let titles: Vec<&str> = update.changes.iter().map(|r| r.title()).collect();
+        assert_eq!(titles, vec!["Preview pacing", "v1.1.1"]);
+        assert_eq!(update.changes[0].body.as_deref(), Some("- Frame budget"));
+        server.join();
+    }
+
+    #[test]
+    fn etag_is_sent_and_not_modified_reuses_the_cache() {
+        let server = Server::replying(vec![json(200, LATEST).header("ETag", "\"abc\""), json(304, "").header("ETag", "\"abc\"")]);
+        let client = UreqClient::new("test");
+        let source = github_at(&server.base);
+        let mut cache = Cache::default();
+
+        let first = check(&client, &source, Channel::Stable, "v1.2.0", &mut cache, NOW, true).unwrap();
+        assert_eq!(cache.etag.as_deref(), Some("\"abc\""));
+        let second = check(&client, &source, Channel::Stable, "v1.2.0", &mut cache, NOW + 10, true).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(cache.checked_at, Some(NOW + 10));
+        let seen = server.join();
+        assert_eq!(seen[0].header("If-None-Match"), None);
+        assert_eq!(seen[1].header("If-None-Match"), Some("\"abc\""));
+    }
+
+    #[test]
+    fn recent_automatic_check_makes_no_request() {
+        // Nothing listens here, so a request would fail
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let source = github_at(&format!("http://127.0.0.1:{}", port));
+        let mut cache = Cache {
+            checked_at: Some(NOW - 60),
+            url: source.releases_url(),
+            etag: Some("\"abc\"".to_string()),
+            body: Some(LATEST.to_string()),
+            retry_at: None,
+        };
+        let client = UreqClient::new("test");
+        let got = check(&client, &source, Channel::Stable, "v1.2.0", &mut cache, NOW, false);
+        assert!(matches!(got, Ok(Check::Available(_))), "{:?}", got);
+
+        // Asked for by the user, or of another source, it goes to the server
+        assert!(check(&client, &source, Channel::Stable, "v1.2.0", &mut cache, NOW, true).is_err());
+        let mirror = Source::Manifest { url: format!("http://127.0.0.1:{}/winhider.json", port) };
+        assert!(check(&client, &mirror, Channel::Stable, "v1.2.0", &mut cache, NOW, false).is_err());
+        let late = NOW + MIN_CHECK_INTERVAL.as_secs() as i64;
+        assert!(check(&client, &source, Channel::Stable, "v1.2.0", &mut cache, late, false).is_err());
+    }
+
+    #[test]
+    fn rate_limit_is_recognised_and_waited_out() {
+        let server = Server::replying(vec![
+            json(403, r#"{"message": "API rate limit exceeded"}"#).header("X-RateLimit-Remaining", 0).header("X-RateLimit-Reset", 1_700_001_500),
+            json(200, LATEST),
+        ]);
+        let client = UreqClient::new("test");
+        let source = github_at(&server.base);
+        let mut cache = Cache::default();
+        let limited = "The update server's rate limit was reached. Try again in 25 minutes.".to_string();
+
+        assert_eq!(check(&client, &source, Channel::Stable, "v1.2.0", &mut cache, NOW, true), Err(limited.clone()));
+        assert_eq!(cache.retry_at, Some(1_700_001_500));
+        // Even a forced check waits, without a request
+        assert_eq!(check(&client, &source, Channel::Stable, "v1.2.0", &mut cache, NOW + 1, true), Err(limited));
+        assert!(check(&client, &source, Channel::Stable, "v1.2.0", &mut cache, 1_700_001_500, true).is_ok());
+        assert_eq!(cache.retry_at, None);
+        assert_eq!(server.join().len(), 2);
+    }
+
+    #[test]
+    fn rate_limit_responses() {
+        let response = |status: u16, headers: &[(&str, &str)]| Response {
+            status,
+            headers: headers.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect(),
+            body: String::new(),
+        };
+        assert_eq!(response(429, &[("retry-after", "30")]).rate_limited_until(NOW), Some(NOW + 30));
+        assert_eq!(response(429, &[]).rate_limited_until(NOW), Some(NOW + DEFAULT_RETRY_SECS));
+        assert_eq!(response(403, &[("Retry-After", "120")]).rate_limited_until(NOW), Some(NOW + 120));
+        assert_eq!(response(403, &[("X-RateLimit-Remaining", "0")]).rate_limited_until(NOW), Some(NOW + DEFAULT_RETRY_SECS));
+        // A plain 403, e.g. a private repository
+        assert_eq!(response(403, &[("X-RateLimit-Remaining", "42")]).rate_limited_until(NOW), None);
+        assert_eq!(response(200, &[("Retry-After", "30")]).rate_limited_until(NOW), None);
+
+        assert_eq!(rate_limit_error(NOW + 30, NOW), "The update server's rate limit was reached. Try again in 30 seconds.");
+        assert_eq!(rate_limit_error(NOW + 3 * 3600, NOW), "The update server's rate limit was reached. Try again in 3 hours.");
+    }
+
+    #[test]
+    fn enterprise_urls_and_descriptions() {
+        let source = Source::GitHub {
+            owner: "tools".to_string(),
+            repo: "winhider".to_string(),
+            api_url: "https://ghe.example.com/api/v3/".to_string(),
+        };
+        assert_eq!(source.releases_url(), "https://ghe.example.com/api/v3/repos/tools/winhider/releases?per_page=30");
+        assert_eq!(source.describe(), "tools/winhider at https://ghe.example.com/api/v3/");
+        assert_eq!(Source::github("aamitn", "winhider").describe(), "GitHub aamitn/winhider");
+    }
+
+    #[test]
+    fn source_is_read_from_settings_json() {
+        let github: Source = serde_json::from_str(r#"{"github": {"owner": "aamitn", "repo": "winhider"}}"#).unwrap();
+        assert_eq!(github, Source::github("aamitn", "winhider"));
+        let manifest: Source = serde_json::from_str(r#"{"manifest": {"url": "https://mirror.example.com/winhider.json"}}"#).unwrap();
+        assert_eq!(manifest.releases_url(), "https://mirror.example.com/winhider.json");
+    }
+
+    #[test]
+    fn channel_is_stored_in_lower_case() {
+        assert_eq!(serde_json::to_string(&Channel::Beta).unwrap(), "\"beta\"");
+        assert_eq!(serde_json::from_str::<Channel>("\"stable\"").unwrap(), Channel::Stable);
+    }
+}
@@ -0,0 +1,376 @@
+/*
+ * =============================================================================
+ * WinHider Verify - Capture Exclusion Check
+ * =============================================================================
+ *
+ * Filename: verify.rs
+ * Description: Decides whether a window really is excluded from capture by
+ *              comparing what Graphics Capture sees where the window is with
+ *              what the window itself draws.
+ *
+ * How it works:
+ * - Points on a grid over the window's interior are mapped into the captured
+ *   monitor frame and into the window's own rendering. Borders and shadows
+ *   are skipped by insetting the window rectangle.
+ * - Each frame pixel is compared with the average of the window pixels it
+ *   covers, so a downscaled frame still lines up with a full-size rendering.
+ * - Points covered by another window are left out; the caller decides which
+ *   points are actually showing the target.
+ * - Mostly matching means still visible. Mostly different (desktop behind
+ *   it, or black with the older affinity mode) means hidden.
+ * =============================================================================
+ */
+
+use serde::Serialize;
+
+const CHANNELS: usize = 4;
+/// Sample points per side of the grid.
+const GRID: i32 = 24;
+/// Fewer usable points than this and there is not enough to go on.
+const MIN_SAMPLES: usize = 24;
+/// Largest mean RGB difference for a point to count as matching.
+const MATCH_TOLERANCE: u32 = 20;
+const VISIBLE_RATIO: f32 = 0.75;
+const HIDDEN_RATIO: f32 = 0.35;
+/// Luminance standard deviation below which the window is a flat colour.
+const FLAT_STDDEV: f32 = 4.0;
+
+/// Rectangle in physical screen pixels, right/bottom exclusive.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ScreenRect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl ScreenRect {
+    pub fn width(&self) -> i32 {
+        self.right - self.left
+    }
+
+    pub fn height(&self) -> i32 {
+        self.bottom - self.top
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width() <= 0 || self.height() <= 0
+    }
+
+    pub fn intersect(&self, other: &ScreenRect) -> ScreenRect {
+        ScreenRect {
+            left: self.left.max(other.left),
+            top: self.top.max(other.top),
+            right: self.right.min(other.right),
+            bottom: self.bottom.min(other.bottom),
+        }
+    }
+}
+
+/// Tightly packed RGBA pixels.
+#[derive(Clone, Copy)]
+pub struct RgbaView<'a> {
+    pub pixels: &'a [u8],
+    pub width: u32,
+    pub height: u32,
+}
+
+impl RgbaView<'_> {
+    fn check(&self, what: &str) -> Result<(), String> {
+        if self.width == 0 || self.height == 0 {
+            return Err(format!("{} is empty", what));
+        }
+        if self.pixels.len() != self.width as usize * self.height as usize * CHANNELS {
+            return Err(format!("{} has {} bytes, expected {}x{} RGBA", what, self.pixels.len(), self.width, self.height));
+        }
+        Ok(())
+    }
+
+    fn pixel(&self, x: u32, y: u32) -> [u32; 3] {
+        let i = (y as usize * self.width as usize + x as usize) * CHANNELS;
+        [self.pixels[i] as u32, self.pixels[i + 1] as u32, self.pixels[i + 2] as u32]
+    }
+
+    /// Mean colour of `[x0, x1) x [y0, y1)`, clamped to the image and at
+    /// least one pixel.
+    fn area_mean(&self, x0: i64, y0: i64, x1: i64, y1: i64) -> [u32; 3] {
+        let clamp = |v: i64, max: u32| v.clamp(0, max as i64 - 1) as u32;
+        let (x0, y0) = (clamp(x0, self.width), clamp(y0, self.height));
+        let (x1, y1) = (clamp(x1 - 1, self.width).max(x0), clamp(y1 - 1, self.height).max(y0));
+        let mut sum = [0u32; 3];
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                for (acc, v) in sum.iter_mut().zip(self.pixel(x, y)) {
+                    *acc += v;
+                }
+            }
+        }
+        let count = (x1 - x0 + 1) * (y1 - y0 + 1);
+        sum.map(|s| (s + count / 2) / count)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Hidden,
+    Visible,
+    Inconclusive,
+}
+
+impl Verdict {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Hidden => "verified hidden",
+            Self::Visible => "still visible",
+            Self::Inconclusive => "inconclusive",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct Verification {
+    pub verdict: Verdict,
+    /// Short human-readable reason.
+    pub detail: String,
+}
+
+impl Verification {
+    pub fn inconclusive(detail: &str) -> Self {
+        Self { verdict: Verdict::Inconclusive, detail: detail.to_string() }
+    }
+}
+
+/// Compares the capture `frame` of `monitor` with the window's own
+/// `rendering`, which covers `window`. `visible(x, y)` says whether the
+/// screen point shows the window rather than something on top of it.
+pub fn compare(
+    frame: &RgbaView,
+    monitor: ScreenRect,
+    rendering: &RgbaView,
+    window: ScreenRect,
+    visible: impl Fn(i32, i32) -> bool,
+) -> Result<Verification, String> {
+    frame.check("Capture frame")?;
+    rendering.check("Window rendering")?;
+    if monitor.is_empty() || window.is_empty() {
+        return Ok(Verification::inconclusive("window or monitor has no area"));
+    }
+
+    // Skip resize borders, title bar shadows and rounded corners
+    let inset_x = (window.width() / 10).max(8);
+    let inset_y = (window.height() / 10).max(8);
+    let interior = ScreenRect {
+        left: window.left + inset_x,
+        top: window.top + inset_y,
+        right: window.right - inset_x,
+        bottom: window.bottom - inset_y,
+    }
+    .intersect(&monitor);
+    if interior.is_empty() {
+        return Ok(Verification::inconclusive("window is too small or not on this monitor"));
+    }
+
+    let (mon_w, mon_h) = (monitor.width() as i64, monitor.height() as i64);
+    let (frame_w, frame_h) = (frame.width as i64, frame.height as i64);
+    let (win_w, win_h) = (window.width() as i64, window.height() as i64);
+    let (ren_w, ren_h) = (rendering.width as i64, rendering.height as i64);
+
+    let mut samples = 0usize;
+    let mut matching = 0usize;
+    let mut luma = Vec::new();
+
+    for gy in 0..GRID {
+        let sy = interior.top + ((2 * gy + 1) * interior.height()) / (2 * GRID);
+        for gx in 0..GRID {
+            let sx = interior.left + ((2 * gx + 1) * interior.width()) / (2 * GRID);
+            if !visible(sx, sy) {
+                continue;
+            }
+
+            // Frame pixel under the point and the screen area it was made from
+            let fx = ((sx - monitor.left) as i64 * frame_w / mon_w).min(frame_w - 1);
+            let fy = ((sy - monitor.top) as i64 * frame_h / mon_h).min(frame_h - 1);
+            let area_x0 = monitor.left as i64 + fx * mon_w / frame_w;
+            let area_x1 = monitor.left as i64 + (fx + 1) * mon_w / frame_w;
+            let area_y0 = monitor.top as i64 + fy * mon_h / frame_h;
+            let area_y1 = monitor.top as i64 + (fy + 1) * mon_h / frame_h;
+
+            // Same area in the window's rendering
+            let to_ren_x = |x: i64| (x - window.left as i64) * ren_w / win_w;
+            let to_ren_y = |y: i64| (y - window.top as i64) * ren_h / win_h;
+            let expected = rendering.area_mean(to_ren_x(area_x0), to_ren_y(area_y0), to_ren_x(area_x1), to_ren_y(area_y1));
+            let captured = frame.pixel(fx as u32, fy as u32);
+
+            let diff: u32 = (0..3).map(|c| expected[c].abs_diff(captured[c])).sum::<u32>() / 3;
+            samples += 1;
+            if diff <= MATCH_TOLERANCE {
+                matching += 1;
+            }
+            luma.push((expected[0] * 299 + expected[1] * 587 + expected[2] * 114) as f32 / 1000.0);
+        }
+    }
+
+    if samples < MIN_SAMPLES {
+        return Ok(Verification::inconclusive("window is mostly covered by other windows"));
+    }
+
+    let ratio = matching as f32 / samples as f32;
+    let summary = format!("{:.0}% of {} sample points match the window", ratio * 100.0, samples);
+
+    if ratio >= VISIBLE_RATIO {
+        let mean = luma.iter().sum::<f32>() / luma.len() as f32;
+        let variance = luma.iter().map(|l| (l - mean) * (l - mean)).sum::<f32>() / luma.len() as f32;
+        if variance.sqrt() < FLAT_STDDEV {
+            // A flat window matches whatever flat background is behind it
+            return Ok(Verification::inconclusive("window content is too uniform to tell apart from the background"));
+        }
+        Ok(Verification { verdict: Verdict::Visible, detail: summary })
+    } else if ratio <= HIDDEN_RATIO {
+        Ok(Verification { verdict: Verdict::Hidden, detail: summary })
+    } else {
+        Ok(Verification { verdict: Verdict::Inconclusive, detail: summary })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downscale::{self, Filter};
+
+    const MONITOR: ScreenRect = ScreenRect { left: 0, top: 0, right: 800, bottom: 600 };
+    const WINDOW: ScreenRect = ScreenRect { left: 100, top: 80, right: 500, bottom: 380 };
+
+    fn desktop(x: i32, y: i32) -> [u8; 4] {
+        [20, 60, 100 + (x + y).rem_euclid(50) as u8, 255]
+    }
+
+    fn content(x: i32, y: i32) -> [u8; 4] {
+        [(x * 7 % 256) as u8, (y * 5 % 256) as u8, ((x ^ y) % 256) as u8, 255]
+    }
+
+    /// Full-size RGBA image of `rect` with `paint(screen_x, screen_y)`.
+    fn paint(rect: ScreenRect, paint: impl Fn(i32, i32) -> [u8; 4]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for y in rect.top..rect.bottom {
+            for x in rect.left..rect.right {
+                out.extend_from_slice(&paint(x, y));
+            }
+        }
+        out
+    }
+
+    fn rendering() -> Vec<u8> {
+        paint(WINDOW, |x, y| content(x - WINDOW.left, y - WINDOW.top))
+    }
+
+    fn view(pixels: &[u8], width: i32, height: i32) -> RgbaView<'_> {
+        RgbaView { pixels, width: width as u32, height: height as u32 }
+    }
+
+    fn composite(window_shows: impl Fn(i32, i32) -> Option<[u8; 4]>) -> Vec<u8> {
+        paint(MONITOR, |x, y| window_shows(x, y).unwrap_or_else(|| desktop(x, y)))
+    }
+
+    fn inside(rect: ScreenRect, x: i32, y: i32) -> bool {
+        (rect.left..rect.right).contains(&x) && (rect.top..rect.bottom).contains(&y)
+    }
+
+    fn with_window(x: i32, y: i32) -> Option<[u8; 4]> {
+        inside(WINDOW, x, y).then(|| content(x - WINDOW.left, y - WINDOW.top))
+    }
+
+    fn check(frame: &[u8], frame_w: i32, frame_h: i32, visible: impl Fn(i32, i32) -> bool) -> Verification {
+        let ren = rendering();
+        compare(&view(frame, frame_w, frame_h), MONITOR, &view(&ren, WINDOW.width(), WINDOW.height()), WINDOW, visible).unwrap()
+    }
+
+    #[test]
+    fn window_in_the_frame_is_visible() {
+        let frame = composite(with_window);
+        assert_eq!(check(&frame, 800, 600, |_, _| true).verdict, Verdict::Visible);
+    }
+
+    #[test]
+    fn desktop_where_the_window_is_means_hidden() {
+        let frame = composite(|_, _| None);
+        assert_eq!(check(&frame, 800, 600, |_, _| true).verdict, Verdict::Hidden);
+    }
+
+    #[test]
+    fn black_region_means_hidden() {
+        let frame = composite(|x, y| with_window(x, y).map(|_| [0, 0, 0, 255]));
+        assert_eq!(check(&frame, 800, 600, |_, _| true).verdict, Verdict::Hidden);
+    }
+
+    #[test]
+    fn works_against_a_downscaled_frame() {
+        for (w, h) in [(400, 300), (640, 480), (333, 250)] {
+            let visible = downscale::downscale(&composite(with_window), 800, 600, w, h, Filter::Box).unwrap();
+            assert_eq!(check(&visible, w as i32, h as i32, |_, _| true).verdict, Verdict::Visible, "{}x{}", w, h);
+            let hidden = downscale::downscale(&composite(|_, _| None), 800, 600, w, h, Filter::Box).unwrap();
+            assert_eq!(check(&hidden, w as i32, h as i32, |_, _| true).verdict, Verdict::Hidden, "{}x{}", w, h);
+        }
+    }
+
+    #[test]
+    fn window_on_a_secondary_monitor() {
+        let monitor = ScreenRect { left: -800, top: 0, right: 0, bottom: 600 };
+        let window = ScreenRect { left: -700, top: 80, right: -300, bottom: 380 };
+        let frame = paint(monitor, |x, y| {
+            if inside(window, x, y) { content(x - window.left, y - window.top) } else { desktop(x, y) }
+        });
+        let ren = rendering();
+        let result = compare(&view(&frame, 800, 600), monitor, &view(&ren, 400, 300), window, |_, _| true).unwrap();
+        assert_eq!(result.verdict, Verdict::Visible);
+    }
+
+    #[test]
+    fn partly_off_monitor_uses_the_part_on_it() {
+        let window = ScreenRect { left: 600, top: 80, right: 1000, bottom: 380 };
+        let frame = composite(|x, y| (x >= window.left && y >= window.top && y < window.bottom).then(|| content(x - window.left, y - window.top)));
+        let ren = rendering();
+        let result = compare(&view(&frame, 800, 600), MONITOR, &view(&ren, 400, 300), window, |_, _| true).unwrap();
+        assert_eq!(result.verdict, Verdict::Visible);
+    }
+
+    #[test]
+    fn covered_window_is_inconclusive() {
+        let frame = composite(|_, _| None);
+        assert_eq!(check(&frame, 800, 600, |_, _| false).verdict, Verdict::Inconclusive);
+        // Only a corner showing
+        let corner = |x: i32, y: i32| x < WINDOW.left + 60 && y < WINDOW.top + 60;
+        assert_eq!(check(&frame, 800, 600, corner).verdict, Verdict::Inconclusive);
+    }
+
+    #[test]
+    fn flat_window_matching_flat_background_is_inconclusive() {
+        let grey = [128, 128, 128, 255];
+        let frame = paint(MONITOR, |_, _| grey);
+        let ren = paint(WINDOW, |_, _| grey);
+        let result = compare(&view(&frame, 800, 600), MONITOR, &view(&ren, 400, 300), WINDOW, |_, _| true).unwrap();
+        assert_eq!(result.verdict, Verdict::Inconclusive);
+    }
+
+    #[test]
+    fn half_matching_is_inconclusive() {
+        let frame = composite(|x, y| with_window(x, y).filter(|_| x < 300));
+        assert_eq!(check(&frame, 800, 600, |_, _| true).verdict, Verdict::Inconclusive);
+    }
+
+    #[test]
+    fn off_monitor_window_is_inconclusive() {
+        let window = ScreenRect { left: 900, top: 80, right: 1300, bottom: 380 };
+        let frame = composite(|_, _| None);
+        let ren = rendering();
+        let result = compare(&view(&frame, 800, 600), MONITOR, &view(&ren, 400, 300), window, |_, _| true).unwrap();
+        assert_eq!(result.verdict, Verdict::Inconclusive);
+    }
+
+    #[test]
+    fn malformed_images_are_errors() {
+        let ren = rendering();
+        assert!(compare(&view(&[0; 10], 800, 600), MONITOR, &view(&ren, 400, 300), WINDOW, |_, _| true).is_err());
+        assert!(compare(&view(&[], 0, 0), MONITOR, &view(&ren, 400, 300), WINDOW, |_, _| true).is_err());
+    }
+}
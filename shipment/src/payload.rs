@@ -56,9 +56,14 @@ pub extern "system" fn DllMain(
 
 unsafe fn apply_stealth() {
     // 1. Parse Filename to determine actions
-    let mut path_buffer = [0u16; 1024];
-    let len = GetModuleFileNameW(DLL_INSTANCE, &mut path_buffer);
-    let full_path = String::from_utf16_lossy(&path_buffer[..len as usize]).to_lowercase();
+    // Grow the buffer until the whole (possibly long, non-ASCII) path fits
+    let mut path_buffer = vec![0u16; 1024];
+    let mut len = GetModuleFileNameW(DLL_INSTANCE, &mut path_buffer) as usize;
+    while len == path_buffer.len() && path_buffer.len() < 32768 {
+        path_buffer.resize(path_buffer.len() * 2, 0);
+        len = GetModuleFileNameW(DLL_INSTANCE, &mut path_buffer) as usize;
+    }
+    let full_path = String::from_utf16_lossy(&path_buffer[..len]).to_lowercase();
     
     // We use a bitmask to pass instructions to the enumeration callback
     // Bit 0: Hide Capture